@@ -0,0 +1,184 @@
+//! Detection of the color scheme preferred by the desktop, used by the `"auto"` theme.
+//! On Linux the preference is read from the XDG settings portal, on macOS and Windows from the system settings.
+//! For testing, the `SBT_COLOR_SCHEME` environment variable or a `color-scheme` file next to the settings file
+//! can be set to `dark` or `light` to take the place of the desktop preference.
+
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, Stream};
+use iced::stream;
+use std::time::Duration;
+
+use crate::settings;
+
+/// The environment variable that overrides the desktop preference
+static COLOR_SCHEME_ENV: &str = "SBT_COLOR_SCHEME";
+/// The name of the file that overrides the desktop preference
+static COLOR_SCHEME_FILE_NAME: &str = "color-scheme";
+/// How often the preference is checked where we can't be notified of changes. The editor only watches while
+/// the window is shown, and checks right away when it is shown again, so this can be long.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The color scheme preferred by the desktop
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorScheme {
+    /// Light text on a dark background
+    #[default]
+    Dark,
+    /// Dark text on a light background
+    Light,
+}
+
+impl ColorScheme {
+    /// Parses `dark` or `light`, as used by the overrides
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "dark" => Some(ColorScheme::Dark),
+            "light" => Some(ColorScheme::Light),
+            _ => None,
+        }
+    }
+}
+
+/// Watches the preferred color scheme. The current scheme is sent right away and again whenever it changes.
+pub fn watch() -> impl Stream<Item = ColorScheme> {
+    stream::channel(10, |mut sender| async move {
+        if let Some(scheme) = std::env::var(COLOR_SCHEME_ENV).ok().as_deref().and_then(ColorScheme::parse) {
+            log::info!("Using color scheme from {}: {:?}", COLOR_SCHEME_ENV, scheme);
+            let _ = sender.send(scheme).await;
+            return;
+        }
+
+        let file = settings::config_dir().join(COLOR_SCHEME_FILE_NAME);
+        if file.exists() {
+            log::info!("Using color scheme from: {:?}", file);
+            poll(&mut sender, move || {
+                std::fs::read_to_string(&file).ok().as_deref().and_then(ColorScheme::parse)
+            })
+            .await;
+        } else {
+            watch_system(&mut sender).await;
+        }
+    })
+}
+
+/// Calls the query every [`POLL_INTERVAL`] and sends the scheme whenever it changes.
+/// The query reads a file or runs a command, so it runs on a blocking thread.
+async fn poll<Q>(sender: &mut mpsc::Sender<ColorScheme>, query: Q)
+where
+    Q: Fn() -> Option<ColorScheme> + Clone + Send + 'static,
+{
+    let mut current = None;
+
+    loop {
+        let scheme = tokio::task::spawn_blocking(query.clone()).await.ok().flatten();
+
+        if scheme.is_some() && scheme != current {
+            current = scheme;
+
+            if let Some(scheme) = scheme {
+                log::info!("Color scheme changed to: {:?}", scheme);
+
+                if sender.send(scheme).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Listens to the color scheme of the XDG settings portal
+#[cfg(target_os = "linux")]
+async fn watch_system(sender: &mut mpsc::Sender<ColorScheme>) {
+    use ashpd::desktop::settings::{ColorScheme as PortalScheme, Settings};
+    use iced::futures::StreamExt;
+
+    let convert = |scheme| match scheme {
+        PortalScheme::PreferDark => ColorScheme::Dark,
+        PortalScheme::PreferLight | PortalScheme::NoPreference => ColorScheme::Light,
+    };
+
+    let portal = match Settings::new().await {
+        Ok(portal) => portal,
+        Err(error) => {
+            log::warn!("Failed to connect to the settings portal: {}", error);
+            return;
+        }
+    };
+
+    match portal.color_scheme().await {
+        Ok(scheme) => {
+            if sender.send(convert(scheme)).await.is_err() {
+                return;
+            }
+        }
+        Err(error) => log::warn!("Failed to read the color scheme: {}", error),
+    }
+
+    match portal.receive_color_scheme_changed().await {
+        Ok(mut changes) => {
+            while let Some(scheme) = changes.next().await {
+                log::info!("Color scheme changed to: {:?}", scheme);
+
+                if sender.send(convert(scheme)).await.is_err() {
+                    return;
+                }
+            }
+        }
+        Err(error) => log::warn!("Failed to listen to color scheme changes: {}", error),
+    }
+}
+
+/// Polls the `AppleInterfaceStyle` default, which is only set in dark mode
+#[cfg(target_os = "macos")]
+async fn watch_system(sender: &mut mpsc::Sender<ColorScheme>) {
+    poll(sender, || {
+        let output = std::process::Command::new("defaults")
+            .args(["read", "-g", "AppleInterfaceStyle"])
+            .output()
+            .ok()?;
+
+        Some(if String::from_utf8_lossy(&output.stdout).trim() == "Dark" {
+            ColorScheme::Dark
+        } else {
+            ColorScheme::Light
+        })
+    })
+    .await;
+}
+
+/// Polls the `AppsUseLightTheme` value of the registry
+#[cfg(target_os = "windows")]
+async fn watch_system(sender: &mut mpsc::Sender<ColorScheme>) {
+    use std::os::windows::process::CommandExt;
+
+    /// Keeps `reg` from opening a console window, as SBT has none
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    poll(sender, || {
+        let output = std::process::Command::new("reg")
+            .args([
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ])
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+            .ok()?;
+
+        Some(if String::from_utf8_lossy(&output.stdout).contains("0x0") {
+            ColorScheme::Dark
+        } else {
+            ColorScheme::Light
+        })
+    })
+    .await;
+}
+
+/// Other platforms have no known way to read the preference
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+async fn watch_system(_sender: &mut mpsc::Sender<ColorScheme>) {
+    log::warn!("Following the system color scheme is not supported on this platform");
+}
@@ -0,0 +1,164 @@
+//! Bracket matching and auto-closing pairs. The bracket next to the cursor and its partner are highlighted,
+//! and typing an opening bracket or quote inserts the closing one too. Brackets in strings and comments
+//! are counted like any other, the search does not know about the syntax of the language.
+
+use iced::widget::text_editor::Content;
+
+/// The brackets that are matched
+const BRACKETS: &[(char, char)] = &[('(', ')'), ('[', ']'), ('{', '}')];
+
+/// How many lines away from the cursor the matching bracket is searched for
+const MAX_DISTANCE: usize = 500;
+
+/// The pairs that are closed automatically, if the language has none configured
+pub fn default_pairs(language: &str) -> &'static [(char, char)] {
+    match language {
+        // Apostrophes and lifetimes are not quotes
+        "md" | "markdown" => &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('`', '`'), ('*', '*')],
+        "rs" | "rust" => &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('`', '`')],
+        "txt" => &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
+        _ => &[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\''), ('`', '`')],
+    }
+}
+
+/// Finds the bracket right after or right before the cursor and its partner.
+/// Returns the position of both as line and byte index, the one next to the cursor first.
+pub fn find_match(content: &Content, line: usize, index: usize) -> Option<((usize, usize), (usize, usize))> {
+    let text = content.line(line)?.to_string();
+
+    let after = text.get(index..).and_then(|rest| rest.chars().next()).map(|character| (index, character));
+    let before = text.get(..index).and_then(|rest| rest.char_indices().next_back());
+
+    [after, before].into_iter().flatten().find_map(|(index, character)| {
+        let partner = if let Some((open, close)) = BRACKETS.iter().find(|(open, _)| *open == character) {
+            search_forward(content, (line, index), *open, *close)
+        } else {
+            let (open, close) = BRACKETS.iter().find(|(_, close)| *close == character)?;
+            search_backward(content, (line, index), *open, *close)
+        }?;
+
+        Some(((line, index), partner))
+    })
+}
+
+/// Finds the closing bracket of the opening one at the given position
+fn search_forward(content: &Content, from: (usize, usize), open: char, close: char) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let last = (from.0 + MAX_DISTANCE).min(content.line_count().saturating_sub(1));
+
+    for line in from.0..=last {
+        let text = content.line(line)?.to_string();
+        let start = if line == from.0 { from.1 } else { 0 };
+
+        for (index, character) in text[start..].char_indices() {
+            if character == open {
+                depth += 1;
+            } else if character == close {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some((line, start + index));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Finds the opening bracket of the closing one at the given position
+fn search_backward(content: &Content, from: (usize, usize), open: char, close: char) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let first = from.0.saturating_sub(MAX_DISTANCE);
+
+    for line in (first..=from.0).rev() {
+        let text = content.line(line)?.to_string();
+        let end = if line == from.0 { from.1 + close.len_utf8() } else { text.len() };
+
+        for (index, character) in text[..end].char_indices().rev() {
+            if character == close {
+                depth += 1;
+            } else if character == open {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some((line, index));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Whether typing an opening character should insert its pair, judged by the characters around the cursor.
+/// Pairs are only inserted before whitespace or a closing character, and quotes not right after a word.
+pub fn should_close(pairs: &[(char, char)], open: char, close: char, before: Option<char>, after: Option<char>) -> bool {
+    let free_after = after.is_none_or(|after| after.is_whitespace() || pairs.iter().any(|(_, close)| *close == after));
+    let free_before = open != close || before.is_none_or(|before| !before.is_alphanumeric() && before != open);
+
+    free_after && free_before
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_bracket_after_the_cursor_is_matched_first() {
+        let content = Content::with_text("f(a[0])");
+        assert_eq!(find_match(&content, 0, 1), Some(((0, 1), (0, 6))));
+        assert_eq!(find_match(&content, 0, 3), Some(((0, 3), (0, 5))));
+    }
+
+    #[test]
+    fn the_bracket_before_the_cursor_is_matched_otherwise() {
+        let content = Content::with_text("f(a[0]) x");
+        assert_eq!(find_match(&content, 0, 7), Some(((0, 6), (0, 1))));
+        assert_eq!(find_match(&content, 0, 9), None);
+    }
+
+    #[test]
+    fn nested_brackets_across_lines_are_matched() {
+        let content = Content::with_text("fn a() {\n    if b { c }\n}");
+        assert_eq!(find_match(&content, 0, 7), Some(((0, 7), (2, 0))));
+        assert_eq!(find_match(&content, 2, 0), Some(((2, 0), (0, 7))));
+    }
+
+    #[test]
+    fn unbalanced_brackets_have_no_match() {
+        let content = Content::with_text("(a\nb");
+        assert_eq!(find_match(&content, 0, 0), None);
+    }
+
+    #[test]
+    fn multibyte_characters_are_skipped() {
+        let content = Content::with_text("(é)");
+        assert_eq!(find_match(&content, 0, 0), Some(((0, 0), (0, 3))));
+        assert_eq!(find_match(&content, 0, 4), Some(((0, 3), (0, 0))));
+    }
+
+    #[test]
+    fn pairs_are_closed_before_whitespace_and_closers() {
+        let pairs = default_pairs("rs");
+        assert!(should_close(pairs, '(', ')', None, None));
+        assert!(should_close(pairs, '(', ')', Some('a'), Some(' ')));
+        assert!(should_close(pairs, '(', ')', None, Some(']')));
+        assert!(!should_close(pairs, '(', ')', None, Some('a')));
+    }
+
+    #[test]
+    fn quotes_are_not_closed_after_a_word() {
+        let pairs = default_pairs("py");
+        assert!(should_close(pairs, '"', '"', Some(' '), None));
+        assert!(!should_close(pairs, '\'', '\'', Some('t'), None));
+        assert!(!should_close(pairs, '"', '"', Some('"'), None));
+    }
+
+    #[test]
+    fn apostrophes_are_not_pairs_in_rust() {
+        assert!(!default_pairs("rs").iter().any(|(open, _)| *open == '\''));
+        assert!(default_pairs("js").iter().any(|(open, _)| *open == '\''));
+    }
+}
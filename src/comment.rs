@@ -0,0 +1,272 @@
+//! Toggling comments. The comment tokens are looked up by the language token the highlighter gets.
+//! Lines are commented after their common indentation, so the indentation is kept.
+
+use crate::cursor;
+use iced::widget::text_editor::Content;
+
+/// The comment tokens of a language
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Tokens {
+    /// The token that starts a line comment, like `//`
+    pub line: Option<String>,
+    /// The tokens that open and close a block comment, like `/*` and `*/`
+    pub block: Option<(String, String)>,
+}
+
+/// How toggling a comment changes the text editor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Toggle {
+    /// Replace the selection inside a line with the text
+    Selection(String),
+    /// Replace the lines from the first to the last, then move the cursor to the line and column,
+    /// or select the lines again if there is none
+    Lines {
+        first: usize,
+        last: usize,
+        lines: Vec<String>,
+        cursor: Option<(usize, usize)>,
+    },
+}
+
+/// Toggles a line comment, or a block comment (`true`), on the selection of the text editor.
+/// Languages without one kind of comment use the other one. Returns `None` if the language has no comments.
+pub fn toggle(content: &Content, tokens: &Tokens, block: bool) -> Option<Toggle> {
+    let line_token = tokens.line.as_deref().filter(|_| !block || tokens.block.is_none());
+    let block_tokens = tokens.block.as_ref().filter(|_| line_token.is_none());
+
+    let selection = content.selection();
+    let is_multiline = selection.as_deref().is_some_and(|selection| selection.contains('\n'));
+
+    // A selection inside a line is wrapped on its own
+    if let (Some((open, close)), Some(selection)) = (block_tokens, &selection) {
+        if block && !is_multiline && !selection.is_empty() {
+            return Some(Toggle::Selection(toggle_block_selection(selection, open, close)));
+        }
+    }
+
+    let (first, last) = cursor::selected_lines(content);
+    let selected: Vec<String> = (first..=last)
+        .filter_map(|line| content.line(line).map(|line| line.to_string()))
+        .collect();
+    let lines = match (line_token, block_tokens) {
+        (Some(token), _) => toggle_line_comment(&selected, token),
+        (None, Some((open, close))) => toggle_block_comment(&selected, open, close),
+        (None, None) => return None,
+    };
+
+    // A selection across lines is selected again. Without one, the cursor keeps its place in the text of its line.
+    let cursor = (!is_multiline).then(|| {
+        let (line, index) = content.cursor_position();
+        let (old, new) = (&selected[line - first], &lines[line - first]);
+        let column = old[..index.min(old.len())].chars().count();
+
+        (line, (column + new.chars().count()).saturating_sub(old.chars().count()))
+    });
+
+    Some(Toggle::Lines { first, last, lines, cursor })
+}
+
+/// The comment tokens of the languages SBT knows, if the language has none configured
+pub fn default_tokens(language: &str) -> Tokens {
+    let (line, block) = match language.to_lowercase().as_str() {
+        "rs" | "rust" | "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "cs" | "java" | "js" | "mjs" | "cjs" | "jsx" | "ts"
+        | "tsx" | "go" | "swift" | "kt" | "kts" | "scala" | "dart" | "groovy" | "php" | "d" | "zig" | "m" | "jsonc" => {
+            (Some("//"), Some(("/*", "*/")))
+        }
+        "py" | "python" | "sh" | "bash" | "zsh" | "fish" | "rb" | "ruby" | "pl" | "perl" | "r" | "toml" | "yaml"
+        | "yml" | "dockerfile" | "makefile" | "cmake" | "conf" | "ini" | "properties" | "tcl" | "nim" | "ps1" | "jl"
+        | "elixir" | "ex" | "exs" | "gitignore" => (Some("#"), None),
+        "lua" => (Some("--"), Some(("--[[", "]]"))),
+        "sql" | "hs" | "haskell" | "elm" | "ada" => (Some("--"), Some(("/*", "*/"))),
+        "css" | "scss" | "less" => (None, Some(("/*", "*/"))),
+        "html" | "htm" | "xml" | "svg" | "xhtml" | "vue" | "md" | "markdown" => (None, Some(("<!--", "-->"))),
+        "lisp" | "clj" | "clojure" | "el" | "scm" | "asm" => (Some(";"), None),
+        "tex" | "latex" | "erl" | "erlang" | "matlab" => (Some("%"), None),
+        "vim" => (Some("\""), None),
+        "bat" | "cmd" => (Some("REM"), None),
+        "ml" | "ocaml" => (None, Some(("(*", "*)"))),
+        _ => (None, None),
+    };
+
+    Tokens {
+        line: line.map(str::to_owned),
+        block: block.map(|(open, close)| (open.to_owned(), close.to_owned())),
+    }
+}
+
+/// Comments the lines with a line comment, or uncomments them if all of them are commented.
+/// Blank lines are left alone and don't count.
+pub fn toggle_line_comment(lines: &[String], token: &str) -> Vec<String> {
+    let code_lines = || lines.iter().filter(|line| !line.trim().is_empty());
+
+    if code_lines().all(|line| line[indentation(line)..].starts_with(token)) {
+        return lines
+            .iter()
+            .map(|line| match line[indentation(line)..].strip_prefix(token) {
+                Some(rest) => format!("{}{}", &line[..indentation(line)], rest.strip_prefix(' ').unwrap_or(rest)),
+                None => line.clone(),
+            })
+            .collect();
+    }
+
+    // Every line is commented at the indentation of the least indented line
+    let indentation = code_lines().map(|line| indentation(line)).min().unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.clone()
+            } else {
+                format!("{}{token} {}", &line[..indentation], &line[indentation..])
+            }
+        })
+        .collect()
+}
+
+/// Wraps the lines in a block comment, or unwraps them if they already are one
+pub fn toggle_block_comment(lines: &[String], open: &str, close: &str) -> Vec<String> {
+    let mut lines = lines.to_vec();
+    let first = lines.iter().position(|line| !line.trim().is_empty());
+    let last = lines.iter().rposition(|line| !line.trim().is_empty());
+
+    let (Some(first), Some(last)) = (first, last) else {
+        return lines;
+    };
+
+    let indentation = indentation(&lines[first]);
+    let start = &lines[first][indentation..];
+    let end = lines[last].trim_end();
+    // On a single line the tokens must not overlap
+    let is_commented = start.starts_with(open)
+        && end.ends_with(close)
+        && (first != last || start.trim_end().len() >= open.len() + close.len());
+
+    if is_commented {
+        let rest = &lines[first][indentation + open.len()..];
+        lines[first] = format!("{}{}", &lines[first][..indentation], rest.strip_prefix(' ').unwrap_or(rest));
+
+        let end = lines[last].trim_end().len() - close.len();
+        let rest = &lines[last][..end];
+        lines[last] = rest.strip_suffix(' ').unwrap_or(rest).to_owned();
+    } else {
+        lines[first] = format!("{}{open} {}", &lines[first][..indentation], &lines[first][indentation..]);
+        lines[last] = format!("{} {close}", lines[last]);
+    }
+
+    lines
+}
+
+/// Wraps a selection inside a line in a block comment, or unwraps it
+pub fn toggle_block_selection(selection: &str, open: &str, close: &str) -> String {
+    match selection.strip_prefix(open).and_then(|rest| rest.strip_suffix(close)) {
+        Some(inner) => inner.to_owned(),
+        None => format!("{open}{selection}{close}"),
+    }
+}
+
+/// The length of the indentation of a line in bytes
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::widget::text_editor::{Action, Motion};
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn rust() -> Tokens {
+        default_tokens("rs")
+    }
+
+    #[test]
+    fn line_comments_keep_the_common_indentation() {
+        let code = lines(&["    fn main() {", "", "        run();", "    }"]);
+        let commented = toggle_line_comment(&code, "//");
+
+        assert_eq!(commented, lines(&["    // fn main() {", "", "    //     run();", "    // }"]));
+        assert_eq!(toggle_line_comment(&commented, "//"), code);
+    }
+
+    #[test]
+    fn mixed_selections_are_commented() {
+        let mixed = lines(&["// a", "b"]);
+        assert_eq!(toggle_line_comment(&mixed, "//"), lines(&["// // a", "// b"]));
+    }
+
+    #[test]
+    fn uncommenting_works_without_a_space_after_the_token() {
+        assert_eq!(toggle_line_comment(&lines(&["#a", "  # b"]), "#"), lines(&["a", "  b"]));
+    }
+
+    #[test]
+    fn block_comments_wrap_the_lines() {
+        let code = lines(&["  a", "b", ""]);
+        let commented = toggle_block_comment(&code, "/*", "*/");
+
+        assert_eq!(commented, lines(&["  /* a", "b */", ""]));
+        assert_eq!(toggle_block_comment(&commented, "/*", "*/"), code);
+    }
+
+    #[test]
+    fn overlapping_block_tokens_are_not_a_comment() {
+        assert_eq!(toggle_block_comment(&lines(&["/*/"]), "/*", "*/"), lines(&["/* /*/ */"]));
+        assert_eq!(toggle_block_comment(&lines(&["/**/"]), "/*", "*/"), lines(&[""]));
+    }
+
+    #[test]
+    fn block_selections_toggle() {
+        assert_eq!(toggle_block_selection("x", "<!--", "-->"), "<!--x-->");
+        assert_eq!(toggle_block_selection("<!--x-->", "<!--", "-->"), "x");
+    }
+
+    #[test]
+    fn languages_fall_back_to_the_other_kind_of_comment() {
+        assert_eq!(default_tokens("PY").line.as_deref(), Some("#"));
+        assert!(default_tokens("css").line.is_none());
+        assert!(default_tokens("unknown") == Tokens::default());
+
+        let content = Content::with_text("a {}");
+        let Some(Toggle::Lines { lines: toggled, .. }) = toggle(&content, &default_tokens("css"), false) else {
+            panic!("css has block comments");
+        };
+        assert_eq!(toggled, lines(&["/* a {} */"]));
+        assert!(toggle(&content, &Tokens::default(), false).is_none());
+    }
+
+    #[test]
+    fn the_cursor_keeps_its_place() {
+        let mut content = Content::with_text("    let a = 1;");
+        cursor::move_to(&mut content, 0, 8);
+
+        let toggled = toggle(&content, &rust(), false);
+        assert_eq!(
+            toggled,
+            Some(Toggle::Lines { first: 0, last: 0, lines: lines(&["    // let a = 1;"]), cursor: Some((0, 11)) })
+        );
+    }
+
+    #[test]
+    fn a_selection_inside_a_line_is_wrapped() {
+        let mut content = Content::with_text("let a = 1;");
+        cursor::select(&mut content, (0, 8), (0, 9));
+
+        assert_eq!(toggle(&content, &rust(), true), Some(Toggle::Selection("/*1*/".to_owned())));
+    }
+
+    #[test]
+    fn a_selection_ending_at_the_start_of_a_line_leaves_that_line_alone() {
+        // Shift+Down twice from column 0 puts the cursor on a line outside the selected lines
+        let mut content = Content::with_text("a\nb\nc");
+        content.perform(Action::Select(Motion::Down));
+        content.perform(Action::Select(Motion::Down));
+        assert_eq!(content.cursor_position(), (2, 0));
+
+        let toggled = toggle(&content, &rust(), false);
+        assert_eq!(toggled, Some(Toggle::Lines { first: 0, last: 1, lines: lines(&["// a", "// b"]), cursor: None }));
+    }
+}
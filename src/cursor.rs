@@ -0,0 +1,231 @@
+//! Helpers to move the cursor of the editor content. Iced does not let us set the cursor directly,
+//! so every helper here is built on top of the regular motion actions of the text editor.
+
+use iced::widget::text_editor::{Action, Content, Motion};
+use std::cmp::Ordering;
+
+/// The distance in lines above which the cursor moves a page at a time, more than any page holds
+const PAGE_DISTANCE: usize = 500;
+
+/// Moves the cursor to the given zero-based line and character column.
+/// Both values are clamped to the document, so it is always safe to pass values out of range.
+/// The cursor moves from where it is, or from the start or end of the document if that is closer.
+pub fn move_to(content: &mut Content, line: usize, column: usize) {
+    // With a selection the first motion only collapses it
+    if content.selection().is_some() {
+        content.perform(Action::Move(Motion::Left));
+    }
+
+    let last = content.line_count().saturating_sub(1);
+    let line = line.min(last);
+    let distance = content.cursor_position().0.abs_diff(line);
+
+    if line < distance {
+        content.perform(Action::Move(Motion::DocumentStart));
+    } else if last - line < distance {
+        content.perform(Action::Move(Motion::DocumentEnd));
+    }
+
+    walk_to(content, line, column, Action::Move);
+}
+
+/// Selects from the given zero-based line and character column to another one
+pub fn select(content: &mut Content, from: (usize, usize), to: (usize, usize)) {
+    move_to(content, from.0, from.1);
+    walk_to(content, to.0, to.1, Action::Select);
+}
+
+/// Selects the given zero-based lines as a whole, leaving the cursor at the end of the last one
+pub fn select_lines(content: &mut Content, first: usize, last: usize) {
+    let end = content.line(last).map(|text| text.chars().count()).unwrap_or(0);
+    select(content, (first, 0), (last, end));
+}
+
+/// The first and the last line touched by the selection, or the line of the cursor if nothing is selected.
+/// A selection ending at the start of a line does not touch that line.
+pub fn selected_lines(content: &Content) -> (usize, usize) {
+    let (line, index) = content.cursor_position();
+    let Some(selection) = content.selection() else {
+        return (line, line);
+    };
+
+    let breaks = selection.matches('\n').count();
+    if breaks == 0 {
+        return (line, line);
+    }
+
+    // Iced only tells where the cursor is, so check on which side of it the selected text is
+    let first_part = selection.split('\n').next().unwrap_or_default();
+    let anchor_above = line.checked_sub(breaks).and_then(|first| {
+        let text = content.line(first)?;
+        let start = text.len().checked_sub(first_part.len())?;
+        (text_between(content, (first, start), (line, index)) == selection).then_some(first)
+    });
+
+    match anchor_above {
+        Some(first) if index == 0 => (first, line - 1),
+        Some(first) => (first, line),
+        None if selection.ends_with('\n') => (line, line + breaks - 1),
+        None => (line, line + breaks),
+    }
+}
+
+/// The text between two positions given as line and byte index
+fn text_between(content: &Content, from: (usize, usize), to: (usize, usize)) -> String {
+    let mut text = String::new();
+
+    for line in from.0..=to.0 {
+        let Some(line_text) = content.line(line) else {
+            break;
+        };
+        let start = if line == from.0 { from.1 } else { 0 };
+        let end = if line == to.0 { to.1 } else { line_text.len() };
+
+        if line > from.0 {
+            text.push('\n');
+        }
+        text.push_str(line_text.get(start..end).unwrap_or_default());
+    }
+
+    text
+}
+
+/// Moves or selects from the current cursor position to the given line and character column
+fn walk_to(content: &mut Content, line: usize, column: usize, action: fn(Motion) -> Action) {
+    let line = line.min(content.line_count().saturating_sub(1));
+
+    // Far lines are approached a page per action. Iced has no motion to a line, so this only saves actions,
+    // the text editor still lays out every line it passes. Without a size, before the first layout, pages don't move.
+    let mut can_page = true;
+
+    // `Up` and `Down` move by visual lines, so a wrapped line may need several steps
+    loop {
+        let before = content.cursor_position();
+        let is_far = can_page && before.0.abs_diff(line) > PAGE_DISTANCE;
+        let motion = match (before.0.cmp(&line), is_far) {
+            (Ordering::Less, true) => Motion::PageDown,
+            (Ordering::Less, false) => Motion::Down,
+            (Ordering::Greater, true) => Motion::PageUp,
+            (Ordering::Greater, false) => Motion::Up,
+            (Ordering::Equal, _) => break,
+        };
+        content.perform(action(motion));
+
+        if content.cursor_position() == before {
+            if !is_far {
+                break;
+            }
+            can_page = false;
+        }
+    }
+
+    let target = content
+        .line(line)
+        .map(|text| byte_index(&text, column))
+        .unwrap_or(0);
+
+    // The cursor moves a grapheme at a time, so it stops on the first step that reaches or passes the target
+    let forward = content.cursor_position().1 < target;
+
+    loop {
+        let before = content.cursor_position();
+
+        if before.0 != line || (forward && before.1 >= target) || (!forward && before.1 <= target) {
+            break;
+        }
+
+        content.perform(action(if forward { Motion::Right } else { Motion::Left }));
+
+        if content.cursor_position() == before {
+            break;
+        }
+    }
+}
+
+/// Converts a character column to a byte index of the given line, clamped to the line length.
+pub fn byte_index(line: &str, column: usize) -> usize {
+    line.char_indices()
+        .nth(column)
+        .map(|(index, _)| index)
+        .unwrap_or(line.len())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_to_a_line_and_column() {
+        let mut content = Content::with_text("first\nsecond\nthird");
+
+        move_to(&mut content, 1, 3);
+        assert_eq!(content.cursor_position(), (1, 3));
+        move_to(&mut content, 0, 2);
+        assert_eq!(content.cursor_position(), (0, 2));
+        move_to(&mut content, 2, 5);
+        assert_eq!(content.cursor_position(), (2, 5));
+    }
+
+    #[test]
+    fn moves_far_without_a_layout() {
+        let mut content = Content::with_text(&"line\n".repeat(3 * PAGE_DISTANCE));
+
+        move_to(&mut content, PAGE_DISTANCE + 20, 2);
+        assert_eq!(content.cursor_position(), (PAGE_DISTANCE + 20, 2));
+
+        select(&mut content, (PAGE_DISTANCE + 20, 0), (10, 1));
+        assert_eq!(content.cursor_position(), (10, 1));
+    }
+
+    #[test]
+    fn clamps_to_the_document() {
+        let mut content = Content::with_text("a\nbc");
+
+        move_to(&mut content, 10, 10);
+        assert_eq!(content.cursor_position(), (1, 2));
+    }
+
+    #[test]
+    fn stops_at_a_grapheme_boundary() {
+        // The combining accent can't be reached on its own, this used to loop forever
+        let mut content = Content::with_text("e\u{301}x");
+
+        move_to(&mut content, 0, 1);
+        assert_eq!(content.cursor_position(), (0, 3));
+        move_to(&mut content, 0, 3);
+        move_to(&mut content, 0, 1);
+        assert_eq!(content.cursor_position(), (0, 0));
+    }
+
+    #[test]
+    fn moves_out_of_a_selection() {
+        let mut content = Content::with_text("a\nb\nc");
+        select(&mut content, (2, 0), (0, 1));
+
+        move_to(&mut content, 1, 1);
+        assert_eq!(content.cursor_position(), (1, 1));
+        assert_eq!(content.selection(), None);
+    }
+
+    #[test]
+    fn selects_lines() {
+        let mut content = Content::with_text("a\nb\nc");
+
+        select_lines(&mut content, 0, 1);
+        assert_eq!(content.selection().as_deref(), Some("a\nb"));
+        assert_eq!(selected_lines(&content), (0, 1));
+    }
+
+    #[test]
+    fn selected_lines_follow_the_direction_of_the_selection() {
+        let mut content = Content::with_text("a\nb\nc\nd");
+
+        select(&mut content, (3, 0), (1, 0));
+        assert_eq!(selected_lines(&content), (1, 2));
+        select(&mut content, (1, 0), (3, 0));
+        assert_eq!(selected_lines(&content), (1, 2));
+        select(&mut content, (1, 1), (2, 1));
+        assert_eq!(selected_lines(&content), (1, 2));
+    }
+}
@@ -0,0 +1,593 @@
+//! The document is the text of the open file, kept in a rope. It is the source of truth for loading and saving,
+//! while the text editor only holds what is needed to show and edit the text.
+//! Files are streamed into and out of the rope, so a large file is never copied into a single string.
+//! After every edit the lines around the cursor are copied from the text editor into the rope.
+//!
+//! Keeping the text twice costs memory: a 20 MiB log takes about 125 MB in the text editor and 23 MB in the rope.
+//! The text editor can't be read outside of the UI thread, so saving without the rope would mean writing the file
+//! on the UI thread or copying the whole text first. A rope is cloned for free, so saving copies nothing.
+
+use iced::widget::text_editor;
+use ropey::{Rope, RopeBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The amount of bytes pasted into the text editor at once while it is filled
+const CONTENT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The line ending a file is written with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    /// Every line ending
+    pub const ALL: [LineEnding; 3] = [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr];
+
+    /// The characters of the line ending
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// The name of the line ending, as in EditorConfig
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::CrLf => "crlf",
+            LineEnding::Cr => "cr",
+        }
+    }
+
+    /// Finds a line ending by its name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|line_ending| line_ending.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// The character set a file is read and written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Charset {
+    #[default]
+    Utf8,
+    /// UTF-8 starting with a byte order mark
+    Utf8Bom,
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Charset {
+    /// Every character set
+    pub const ALL: [Charset; 5] = [Charset::Utf8, Charset::Utf8Bom, Charset::Latin1, Charset::Utf16Le, Charset::Utf16Be];
+
+    /// The name of the character set, as in EditorConfig
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "utf-8",
+            Charset::Utf8Bom => "utf-8-bom",
+            Charset::Latin1 => "latin1",
+            Charset::Utf16Le => "utf-16le",
+            Charset::Utf16Be => "utf-16be",
+        }
+    }
+
+    /// Finds a character set by its name
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|charset| charset.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Decodes a whole file. Invalid sequences are replaced.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Charset::Utf8 | Charset::Utf8Bom => String::from_utf8_lossy(bytes).into_owned(),
+            Charset::Latin1 => bytes.iter().map(|byte| char::from(*byte)).collect(),
+            Charset::Utf16Le | Charset::Utf16Be => {
+                let units = bytes.chunks_exact(2).map(|pair| match self {
+                    Charset::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                });
+                char::decode_utf16(units)
+                    .map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+        }
+    }
+
+    /// Encodes text, failing on characters the character set can't hold
+    fn encode(self, text: &str, bytes: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Charset::Utf8 | Charset::Utf8Bom => bytes.extend_from_slice(text.as_bytes()),
+            Charset::Latin1 => {
+                for character in text.chars() {
+                    let byte = u8::try_from(character).map_err(|_| {
+                        io::Error::new(io::ErrorKind::InvalidData, format!("{character:?} can't be written in latin1"))
+                    })?;
+                    bytes.push(byte);
+                }
+            }
+            Charset::Utf16Le => bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            Charset::Utf16Be => bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+        }
+        Ok(())
+    }
+
+    /// The byte order mark the file starts with
+    fn byte_order_mark(self) -> &'static [u8] {
+        match self {
+            Charset::Utf8 | Charset::Latin1 => &[],
+            Charset::Utf8Bom => &[0xef, 0xbb, 0xbf],
+            Charset::Utf16Le => &[0xff, 0xfe],
+            Charset::Utf16Be => &[0xfe, 0xff],
+        }
+    }
+}
+
+/// How a document is written to a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Format {
+    /// The line ending of every line
+    pub line_ending: LineEnding,
+    /// The character set of the file
+    pub charset: Charset,
+}
+
+/// The text of a document. Lines are separated by `\n`, like the text of the text editor.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    /// The text of the document
+    rope: Rope,
+}
+
+/// The part of the text editor an edit can touch, taken right before the edit
+#[derive(Debug, Clone, Copy)]
+pub struct Edit {
+    /// The line of the cursor
+    line: usize,
+    /// The amount of line breaks in the selection, which reaches this far above or below the cursor
+    selected_lines: usize,
+    /// The amount of lines of the text editor
+    line_count: usize,
+}
+
+/// The lines an edit replaced, and the lines it replaced them with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The first line that was replaced
+    pub line: usize,
+    /// The lines before the edit
+    pub old: Vec<String>,
+    /// The lines after the edit
+    pub new: Vec<String>,
+}
+
+impl Edit {
+    /// Records the state of the text editor before an edit is performed
+    pub fn before(content: &text_editor::Content) -> Self {
+        Edit {
+            line: content.cursor_position().0,
+            selected_lines: content
+                .selection()
+                .map(|selection| selection.matches('\n').count())
+                .unwrap_or(0),
+            line_count: content.line_count(),
+        }
+    }
+}
+
+impl Document {
+    /// Reads a file into a new document, together with the character set it was read in.
+    /// Line endings are normalized to `\n`. Files in a known character set are decoded from it,
+    /// without their byte order mark.
+    pub async fn load(path: PathBuf, charset: Option<Charset>) -> io::Result<(Self, Charset)> {
+        tokio::task::spawn_blocking(move || match charset {
+            None => Document::read(File::open(path)?),
+            Some(charset) => Ok((Document::decode(&fs::read(path)?, charset)?, charset)),
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Reads a new document from a reader, together with the character set it was read in.
+    /// Line endings are normalized to `\n`. Text that is not valid UTF-8 is read again from the start
+    /// as Latin-1, in which every byte is a character, so saving it again writes the same bytes.
+    pub fn read(mut reader: impl Read + Seek) -> io::Result<(Self, Charset)> {
+        match Document::read_utf8(&mut reader) {
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                log::info!("The text is not valid UTF-8, reading it as Latin-1");
+                let mut bytes = Vec::new();
+                reader.rewind()?;
+                reader.read_to_end(&mut bytes)?;
+
+                Ok((Document::decode(&bytes, Charset::Latin1)?, Charset::Latin1))
+            }
+            result => result.map(|document| (document, Charset::Utf8)),
+        }
+    }
+
+    /// Decodes a whole file in a character set, without its byte order mark
+    fn decode(bytes: &[u8], charset: Charset) -> io::Result<Self> {
+        let bytes = bytes.strip_prefix(charset.byte_order_mark()).unwrap_or(bytes);
+        let text = charset.decode(bytes);
+
+        Document::read_utf8(text.strip_prefix('\u{feff}').unwrap_or(&text).as_bytes())
+    }
+
+    /// Streams UTF-8 text into a new document. Line endings are normalized to `\n`.
+    fn read_utf8(reader: impl Read) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let mut builder = RopeBuilder::new();
+        let mut line = String::new();
+
+        while reader.read_line(&mut line)? > 0 {
+            if line.ends_with("\r\n") {
+                line.truncate(line.len() - 2);
+                line.push('\n');
+            }
+
+            // Old Mac line endings
+            if line.contains('\r') {
+                line = line.replace('\r', "\n");
+            }
+
+            builder.append(&line);
+            line.clear();
+        }
+
+        Ok(Document { rope: builder.finish() })
+    }
+
+    /// Writes the document to a file in the given format. The whole text is encoded before the file is
+    /// touched, so a character the charset can't hold leaves the file as it was.
+    /// The text is written as it is, the save transformations have already run on it.
+    pub async fn save(self, path: PathBuf, format: Format) -> io::Result<()> {
+        tokio::task::spawn_blocking(move || fs::write(path, self.encode(format)?))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    /// Encodes the text in the given format, a line at a time
+    fn encode(&self, format: Format) -> io::Result<Vec<u8>> {
+        let mut bytes = format.charset.byte_order_mark().to_vec();
+        let mut line = String::new();
+
+        for index in 0..self.rope.len_lines() {
+            line.clear();
+            line.extend(self.rope.line(index).chunks());
+
+            match line.strip_suffix('\n') {
+                Some(text) => {
+                    format.charset.encode(text, &mut bytes)?;
+                    format.charset.encode(format.line_ending.as_str(), &mut bytes)?;
+                }
+                None => format.charset.encode(&line, &mut bytes)?,
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Creates a document from the text of the text editor
+    pub fn from_content(content: &text_editor::Content) -> Self {
+        let mut builder = RopeBuilder::new();
+
+        for (index, line) in content.lines().enumerate() {
+            if index > 0 {
+                builder.append("\n");
+            }
+            builder.append(&line);
+        }
+
+        Document { rope: builder.finish() }
+    }
+
+    /// Fills a new text editor with the document, a chunk at a time
+    pub fn to_content(&self) -> text_editor::Content {
+        let mut content = text_editor::Content::new();
+        let mut chunk = String::with_capacity(CONTENT_CHUNK_SIZE);
+
+        for piece in self.rope.chunks() {
+            chunk.push_str(piece);
+
+            if chunk.len() >= CONTENT_CHUNK_SIZE {
+                let full = std::mem::replace(&mut chunk, String::with_capacity(CONTENT_CHUNK_SIZE));
+                content.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(full))));
+            }
+        }
+
+        if !chunk.is_empty() {
+            content.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(chunk))));
+        }
+
+        content.perform(text_editor::Action::Move(text_editor::Motion::DocumentStart));
+        content
+    }
+
+    /// Copies the lines an edit touched from the text editor into the document and returns them.
+    /// Falls back to copying the whole text if the document went out of sync, which returns `None`.
+    pub fn apply(&mut self, edit: Edit, content: &text_editor::Content) -> Option<Change> {
+        let line_count = content.line_count();
+        let delta = line_count as isize - edit.line_count as isize;
+        let line = content.cursor_position().0;
+
+        // The edit replaced lines between the cursor before and after it, widened by the selection
+        let start = edit.line.min(line).saturating_sub(edit.selected_lines);
+        let end = (edit.line as isize + delta)
+            .max(line as isize)
+            .saturating_add(1 + edit.selected_lines as isize)
+            .min(line_count as isize);
+        let old_end = end - delta;
+
+        if old_end < start as isize || old_end as usize > self.rope.len_lines() || end < start as isize {
+            log::warn!("The document is out of sync with the editor, copying the whole text");
+            *self = Document::from_content(content);
+            return None;
+        }
+
+        let (end, old_end) = (end as usize, old_end as usize);
+
+        let old_range = self.rope.line_to_char(start)..if old_end < edit.line_count {
+            self.rope.line_to_char(old_end)
+        } else {
+            self.rope.len_chars()
+        };
+
+        let old: Vec<String> = (start..old_end)
+            .map(|index| {
+                let mut line = self.rope.line(index).to_string();
+                if line.ends_with('\n') {
+                    line.pop();
+                }
+                line
+            })
+            .collect();
+        let new: Vec<String> = (start..end)
+            .map(|index| content.line(index).map(|line| line.to_string()).unwrap_or_default())
+            .collect();
+
+        let mut replacement = new.join("\n");
+        if end < line_count {
+            replacement.push('\n');
+        }
+
+        let insert_at = old_range.start;
+        self.rope.remove(old_range);
+        self.rope.insert(insert_at, &replacement);
+
+        if self.rope.len_lines() != line_count {
+            log::warn!("The document is out of sync with the editor, copying the whole text");
+            *self = Document::from_content(content);
+            return None;
+        }
+
+        Some(Change { line: start, old, new })
+    }
+
+    /// The first and the last lines of the document, enough to detect its language
+    pub fn head_and_tail(&self, lines: usize) -> String {
+        let line_count = self.rope.len_lines();
+
+        if line_count <= lines * 2 {
+            return self.rope.to_string();
+        }
+
+        let head = self.rope.slice(..self.rope.line_to_char(lines));
+        let tail = self.rope.slice(self.rope.line_to_char(line_count - lines)..);
+
+        format!("{head}{tail}")
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor;
+    use iced::widget::text_editor::{Action, Content, Motion};
+
+    /// A text editor and its document, edited together like the editor does
+    struct Editor {
+        content: Content,
+        document: Document,
+        /// The change of the last edit
+        change: Option<Change>,
+    }
+
+    impl Editor {
+        fn new(text: &str) -> Self {
+            let content = Content::with_text(text);
+            let document = Document::from_content(&content);
+            Editor { content, document, change: None }
+        }
+
+        fn perform(&mut self, action: Action) {
+            if action.is_edit() {
+                let edit = Edit::before(&self.content);
+                self.content.perform(action);
+                self.change = self.document.apply(edit, &self.content);
+                assert!(self.change.is_some(), "the document went out of sync");
+            } else {
+                self.content.perform(action);
+            }
+        }
+
+        fn edit(&mut self, edit: text_editor::Edit) {
+            self.perform(Action::Edit(edit));
+        }
+
+        fn paste(&mut self, text: &str) {
+            self.edit(text_editor::Edit::Paste(Arc::new(text.to_owned())));
+        }
+
+        fn assert_in_sync(&self) {
+            assert_eq!(self.document.rope.to_string(), text(&self.content));
+        }
+    }
+
+    /// The text of the text editor. `Content::text` ends every line with a line break, even the last one.
+    fn text(content: &Content) -> String {
+        content.lines().map(|line| line.to_string()).collect::<Vec<_>>().join("\n")
+    }
+
+    const TEXT: &str = "zero\none\ntwo\nthree\nfour";
+
+    #[test]
+    fn typing_and_line_breaks() {
+        let mut editor = Editor::new(TEXT);
+        cursor::move_to(&mut editor.content, 2, 1);
+
+        editor.edit(text_editor::Edit::Insert('x'));
+        editor.assert_in_sync();
+        assert_eq!(editor.change, Some(Change { line: 2, old: vec!["two".to_owned()], new: vec!["txwo".to_owned()] }));
+        editor.edit(text_editor::Edit::Enter);
+        editor.assert_in_sync();
+        editor.edit(text_editor::Edit::Backspace);
+        editor.assert_in_sync();
+    }
+
+    #[test]
+    fn multi_line_pastes() {
+        let mut editor = Editor::new(TEXT);
+
+        cursor::move_to(&mut editor.content, 1, 2);
+        editor.paste("a\nb\nc");
+        editor.assert_in_sync();
+
+        cursor::move_to(&mut editor.content, 100, 100);
+        editor.paste("\nend\n");
+        editor.assert_in_sync();
+
+        cursor::move_to(&mut editor.content, 0, 0);
+        editor.paste("start\n\n");
+        editor.assert_in_sync();
+    }
+
+    #[test]
+    fn deletes_across_lines() {
+        let mut editor = Editor::new(TEXT);
+
+        cursor::move_to(&mut editor.content, 2, 0);
+        editor.edit(text_editor::Edit::Backspace);
+        editor.assert_in_sync();
+
+        cursor::move_to(&mut editor.content, 0, 4);
+        editor.edit(text_editor::Edit::Delete);
+        editor.assert_in_sync();
+
+        cursor::select(&mut editor.content, (0, 2), (2, 1));
+        editor.edit(text_editor::Edit::Delete);
+        editor.assert_in_sync();
+    }
+
+    #[test]
+    fn selection_replacements() {
+        // Forward and backward selections, replaced by fewer, as many and more lines
+        for (from, to) in [((1, 1), (3, 2)), ((3, 2), (1, 1)), ((0, 0), (4, 4)), ((4, 4), (0, 0))] {
+            for replacement in ["", "x", "x\ny", "x\ny\nz\nw\nv\nu"] {
+                let mut editor = Editor::new(TEXT);
+                cursor::select(&mut editor.content, from, to);
+                editor.paste(replacement);
+                editor.assert_in_sync();
+
+                let mut editor = Editor::new(TEXT);
+                cursor::select(&mut editor.content, from, to);
+                editor.edit(text_editor::Edit::Insert('q'));
+                editor.assert_in_sync();
+            }
+        }
+    }
+
+    #[test]
+    fn whole_line_selections() {
+        let mut editor = Editor::new(TEXT);
+
+        cursor::select_lines(&mut editor.content, 1, 2);
+        editor.perform(Action::Select(Motion::Right));
+        editor.edit(text_editor::Edit::Backspace);
+        editor.assert_in_sync();
+    }
+
+    #[test]
+    fn reads_every_line_ending() {
+        let document = Document::read_utf8("a\r\nb\rc\nd".as_bytes()).unwrap();
+        assert_eq!(document.rope.to_string(), "a\nb\nc\nd");
+    }
+
+    #[test]
+    fn round_trips_through_the_text_editor() {
+        let document = Document::read_utf8(TEXT.as_bytes()).unwrap();
+        assert_eq!(text(&document.to_content()), TEXT);
+    }
+
+    #[test]
+    fn encodes_charsets() {
+        for charset in [Charset::Utf8, Charset::Latin1, Charset::Utf16Le, Charset::Utf16Be] {
+            let mut bytes = Vec::new();
+            charset.encode("äb", &mut bytes).unwrap();
+            assert_eq!(charset.decode(&bytes), "äb");
+        }
+
+        assert!(Charset::Latin1.encode("€", &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn line_endings_and_charsets_are_found_by_name() {
+        for line_ending in LineEnding::ALL {
+            assert_eq!(LineEnding::parse(line_ending.name()), Some(line_ending));
+        }
+        for charset in Charset::ALL {
+            assert_eq!(Charset::parse(charset.name()), Some(charset));
+        }
+
+        assert_eq!(LineEnding::parse("CRLF"), Some(LineEnding::CrLf));
+        assert_eq!(Charset::parse("UTF-8"), Some(Charset::Utf8));
+        assert_eq!(Charset::parse("utf-32"), None);
+    }
+
+    #[test]
+    fn a_failed_save_leaves_the_file_alone() {
+        let path = std::env::temp_dir().join(format!("sbt-save-{}.txt", std::process::id()));
+        fs::write(&path, b"caf\xe9\n").unwrap();
+
+        let document = Document::read_utf8("price: 5 €\n".as_bytes()).unwrap();
+        let format = Format { line_ending: LineEnding::Lf, charset: Charset::Latin1 };
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let result = runtime.block_on(document.save(path.clone(), format));
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(bytes, b"caf\xe9\n");
+    }
+
+    #[test]
+    fn text_that_is_not_utf8_is_read_as_latin1() {
+        let mut bytes = "ASCII text on a line\r\n".repeat(1000).into_bytes();
+        bytes.extend_from_slice(b"caf\xe9 cr\xe8me\r\n");
+
+        let (document, charset) = Document::read(io::Cursor::new(&bytes)).unwrap();
+        assert_eq!(charset, Charset::Latin1);
+        assert!(document.rope.to_string().ends_with("café crème\n"));
+
+        let format = Format { line_ending: LineEnding::CrLf, charset };
+        assert_eq!(document.encode(format).unwrap(), bytes);
+    }
+
+    #[test]
+    fn utf8_text_is_read_as_utf8() {
+        let (document, charset) = Document::read(io::Cursor::new("café")).unwrap();
+        assert_eq!(charset, Charset::Utf8);
+        assert_eq!(document.rope.to_string(), "café");
+    }
+}
@@ -0,0 +1,203 @@
+//! The dropdown mode shows the window like the console of a Quake game: undecorated, always on top,
+//! attached to an edge of the screen and sliding in and out when the hotkey is pressed.
+//!
+//! Iced can't tell us the size of the screen, except to the function that positions a new window.
+//! [`locate_monitor`] is used as that function and remembers the size for the rest of the dropdown logic.
+//! The window is opened again every time it is shown, so it follows changes of the resolution and of the monitor.
+
+use iced::window;
+use iced::{Point, Size};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long the window takes to slide in or out
+pub const SLIDE_DURATION: Duration = Duration::from_millis(150);
+
+/// How often the window is moved while it slides
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// The size of the monitor the window was last opened on, in logical pixels
+static MONITOR_SIZE: Mutex<Option<Size>> = Mutex::new(None);
+
+/// Turns the settings of the window into the ones of a dropdown window.
+/// The window is sized and moved to its edge once it exists and the size of the monitor is known.
+pub fn apply(settings: window::Settings) -> window::Settings {
+    window::Settings {
+        position: window::Position::SpecificWith(locate_monitor),
+        decorations: false,
+        level: window::Level::AlwaysOnTop,
+        ..settings
+    }
+}
+
+/// Places the new window at the top left corner of its monitor and remembers the size of the monitor
+pub fn locate_monitor(_window: Size, monitor: Size) -> Point {
+    if let Ok(mut size) = MONITOR_SIZE.lock() {
+        *size = Some(monitor);
+    }
+    Point::ORIGIN
+}
+
+/// The size of the monitor the window was last opened on, if it is known
+fn monitor_size() -> Option<Size> {
+    *MONITOR_SIZE.lock().ok()?
+}
+
+/// The edge of the screen the window is attached to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Edge {
+    /// The window hangs from the top of the screen, across its width
+    #[default]
+    Top,
+    /// The window stands on the bottom of the screen, across its width
+    Bottom,
+    /// The window is attached to the left of the screen, across its height
+    Left,
+    /// The window is attached to the right of the screen, across its height
+    Right,
+}
+
+/// A running slide animation
+#[derive(Debug, Clone, Copy)]
+struct Slide {
+    /// When the animation started
+    started: Instant,
+    /// Whether the window slides in or out
+    showing: bool,
+}
+
+/// The state of the dropdown window
+#[derive(Debug)]
+pub struct Dropdown {
+    /// The edge the window is attached to
+    edge: Edge,
+    /// The share of the screen the window covers, between 0 and 1
+    fraction: f32,
+    /// The top left corner of the monitor, known once the window is open
+    origin: Point,
+    /// The running slide animation, if any
+    slide: Option<Slide>,
+}
+
+impl Dropdown {
+    /// Creates the dropdown state. The size is a percentage of the screen.
+    pub fn new(edge: Edge, size: f32) -> Self {
+        Dropdown {
+            edge,
+            fraction: (size / 100.0).clamp(0.1, 1.0),
+            origin: Point::ORIGIN,
+            slide: None,
+        }
+    }
+
+    /// Sets the top left corner of the monitor, which is where [`locate_monitor`] put the window
+    pub fn set_origin(&mut self, origin: Point) {
+        self.origin = origin;
+    }
+
+    /// The size of the window, if the size of the monitor is known
+    pub fn window_size(&self) -> Option<Size> {
+        let monitor = monitor_size()?;
+
+        Some(match self.edge {
+            Edge::Top | Edge::Bottom => Size::new(monitor.width, monitor.height * self.fraction),
+            Edge::Left | Edge::Right => Size::new(monitor.width * self.fraction, monitor.height),
+        })
+    }
+
+    /// The position of the fully visible window
+    pub fn shown_position(&self) -> Option<Point> {
+        let monitor = monitor_size()?;
+        let size = self.window_size()?;
+
+        let (x, y) = match self.edge {
+            Edge::Top | Edge::Left => (0.0, 0.0),
+            Edge::Bottom => (0.0, monitor.height - size.height),
+            Edge::Right => (monitor.width - size.width, 0.0),
+        };
+
+        Some(Point::new(self.origin.x + x, self.origin.y + y))
+    }
+
+    /// The position of the window just outside its edge of the screen
+    pub fn hidden_position(&self) -> Option<Point> {
+        let monitor = monitor_size()?;
+        let size = self.window_size()?;
+
+        let (x, y) = match self.edge {
+            Edge::Top => (0.0, -size.height),
+            Edge::Bottom => (0.0, monitor.height),
+            Edge::Left => (-size.width, 0.0),
+            Edge::Right => (monitor.width, 0.0),
+        };
+
+        Some(Point::new(self.origin.x + x, self.origin.y + y))
+    }
+
+    /// Starts sliding the window in or out
+    pub fn start_slide(&mut self, showing: bool) {
+        self.slide = Some(Slide {
+            started: Instant::now(),
+            showing,
+        });
+    }
+
+    /// Whether the window is sliding
+    pub fn is_sliding(&self) -> bool {
+        self.slide.is_some()
+    }
+
+    /// Advances the slide animation. Returns the position of the window for this frame,
+    /// and whether the animation is finished.
+    pub fn step(&mut self, now: Instant) -> Option<(Point, bool)> {
+        let slide = self.slide?;
+        let shown = self.shown_position()?;
+        let hidden = self.hidden_position()?;
+
+        let progress = (now.duration_since(slide.started).as_secs_f32() / SLIDE_DURATION.as_secs_f32()).min(1.0);
+        // Ease out, so the window slows down towards the end
+        let eased = 1.0 - (1.0 - progress).powi(3);
+
+        let (from, to) = if slide.showing { (hidden, shown) } else { (shown, hidden) };
+        let position = Point::new(from.x + (to.x - from.x) * eased, from.y + (to.y - from.y) * eased);
+
+        let finished = progress >= 1.0;
+        if finished {
+            self.slide = None;
+        }
+
+        Some((position, finished))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_size_of_the_monitor_and_slides() {
+        let mut dropdown = Dropdown::new(Edge::Bottom, 50.0);
+        dropdown.set_origin(Point::new(100.0, 0.0));
+
+        locate_monitor(Size::ZERO, Size::new(800.0, 600.0));
+        assert_eq!(dropdown.window_size(), Some(Size::new(800.0, 300.0)));
+        assert_eq!(dropdown.shown_position(), Some(Point::new(100.0, 300.0)));
+        assert_eq!(dropdown.hidden_position(), Some(Point::new(100.0, 600.0)));
+
+        locate_monitor(Size::ZERO, Size::new(1920.0, 1080.0));
+        assert_eq!(dropdown.window_size(), Some(Size::new(1920.0, 540.0)));
+        assert_eq!(dropdown.shown_position(), Some(Point::new(100.0, 540.0)));
+
+        // The size of the monitor is shared, so the slide is checked in the same test
+        locate_monitor(Size::ZERO, Size::new(800.0, 600.0));
+        let mut dropdown = Dropdown::new(Edge::Left, 25.0);
+
+        dropdown.start_slide(true);
+        let started = dropdown.slide.unwrap().started;
+        assert_eq!(dropdown.step(started), Some((Point::new(-200.0, 0.0), false)));
+        assert_eq!(dropdown.step(started + SLIDE_DURATION), Some((Point::ORIGIN, true)));
+        assert!(!dropdown.is_sliding());
+    }
+}
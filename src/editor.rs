@@ -2,25 +2,61 @@
 //! of the application. It includes both the iced frontend and the logic to manage it.
 
 use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
-use iced::futures::SinkExt;
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, StreamExt};
 use iced::futures::Stream;
 use iced::stream;
+use iced::event::{self, Event};
 use iced::Subscription;
 use iced::keyboard;
+use iced::mouse;
 use iced::window;
 use iced::widget::{
-    self, button, column, container, horizontal_space, row, text,
-    text_editor, tooltip,
+    self, button, column, container, horizontal_space, mouse_area, opaque,
+    row, scrollable, stack, text, text_editor, text_input, tooltip,
 };
-use iced::{Center, Element, Fill, Font, Task, Theme};
-use std::ffi;
+use iced::{font, Center, Element, Fill, Font, Point, Size, Task, Theme};
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
+use crate::appearance::{self, ColorScheme};
+use crate::brackets;
+use crate::comment;
+use crate::cursor;
+use crate::document::{Charset, Document, Edit, Format, LineEnding};
+use crate::dropdown::{self, Dropdown};
+use crate::editorconfig;
+use crate::follow;
+use crate::goto;
+use crate::hex::{self, HexView};
+use crate::highlighter::{self, Highlighter};
+use crate::history::{self, History};
+use crate::indent;
+use crate::keymap::{Command, Keymap};
+use crate::language;
+use crate::lines::{self, Operation};
+use crate::large_file::{self, LargeFile};
+use crate::palette::Palette;
 use crate::settings;
+use crate::transform;
+use crate::window_state::WindowState;
 use crate:: START_KEY;
 
+/// The ID of the prompt input, needed to focus it
+const PROMPT_INPUT_ID: &str = "prompt";
+/// The ID of the command palette input, needed to focus it
+const PALETTE_INPUT_ID: &str = "command_palette";
+/// The name of the directory with the user fonts
+const FONTS_DIR_NAME: &str = "fonts";
+/// The smallest font size the editor can be zoomed to
+const MIN_FONT_SIZE: f32 = 6.0;
+/// The biggest font size the editor can be zoomed to
+const MAX_FONT_SIZE: f32 = 72.0;
+/// How long the window has to stay still before its new geometry is saved
+const WINDOW_STATE_SAVE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
 /// An error that can occur while interacting with the editor
 #[derive(Debug, Clone)]
 pub enum Error {
@@ -31,20 +67,122 @@ pub enum Error {
     IoError(io::ErrorKind),
 }
 
+/// A file read from the file system
+#[derive(Debug, Clone)]
+pub enum Opened {
+    /// A file that is loaded at once, with the character set it was read in and the EditorConfig properties that apply to it
+    Document(Document, Charset, editorconfig::Properties),
+    /// A file above the large file threshold, of which only a window of lines is loaded
+    Large(LargeFile),
+    /// A binary file, shown in the hex view
+    Binary(HexView),
+}
+
+/// The inputs that can be shown above the editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    /// Jump to a line and column
+    GoToLine,
+    /// Set the language of the document
+    SetLanguage,
+    /// Set the line ending the document is saved with
+    SetLineEnding,
+    /// Set the character set the document is saved in
+    SetCharset,
+    /// Jump to an offset of the hex view
+    GoToOffset,
+}
+
+impl Prompt {
+    /// The label shown in front of the input
+    fn label(self) -> &'static str {
+        match self {
+            Prompt::GoToLine => "Go to line:",
+            Prompt::SetLanguage => "Language:",
+            Prompt::SetLineEnding => "Line ending:",
+            Prompt::SetCharset => "Charset:",
+            Prompt::GoToOffset => "Go to offset:",
+        }
+    }
+
+    /// The placeholder of the input
+    fn placeholder(self) -> &'static str {
+        match self {
+            Prompt::GoToLine => "line, line:column, +N or -N",
+            Prompt::SetLanguage => "name or extension, e.g. python or rs (empty to detect)",
+            Prompt::SetLineEnding => "lf, crlf or cr (empty for the default)",
+            Prompt::SetCharset => "utf-8, utf-8-bom, latin1, utf-16le or utf-16be (empty for the default)",
+            Prompt::GoToOffset => "decimal, 0x400 or 400h",
+        }
+    }
+}
+
 /// The editor of the application
 pub struct Editor {
     /// The Iced window ID, needed to execute tasks
     window_id: Option<iced::window::Id>,
     /// The file currently being edited
     file: Option<PathBuf>,
-    /// The content of the editor
+    /// The text of the file, which is loaded and saved
+    document: Document,
+    /// The content of the editor, kept in sync with the document
     content: text_editor::Content,
+    /// The open file, if it is too large to be loaded at once
+    large_file: Option<LargeFile>,
+    /// The hex view of the open file, if it is binary
+    hex_view: Option<HexView>,
     /// Whether a file is currently being loaded
     is_loading: bool,
     /// Whether the file has been modified
     is_dirty: bool,
+    /// Whether editing the document is blocked
+    is_read_only: bool,
+    /// Whether the file can be written, otherwise saving asks for a new path
+    is_writable: bool,
+    /// Whether text appended to the file is added to the editor, like `tail -f`
+    is_following: bool,
+    /// Whether editing was blocked before following started, restored when it stops
+    was_read_only: bool,
     /// Whether the window is visible
     is_visible: bool,
+    /// The size, position and visibility of the window, saved across restarts
+    window_state: WindowState,
+    /// The settings the window is opened with, before the window state is applied
+    window_settings: window::Settings,
+    /// Incremented on every change of the window state, so only the last change is saved
+    window_state_version: u64,
+    /// The state of the dropdown window, if the window is shown as one
+    dropdown: Option<Dropdown>,
+    /// The language of the document, detected when it is opened
+    language: String,
+    /// The language the user picked for the document, overrides the detected one
+    language_override: Option<String>,
+    /// The line ending the user picked for the document, overrides the settings and EditorConfig when saving
+    line_ending_override: Option<LineEnding>,
+    /// The character set the user picked for the document, overrides the settings and EditorConfig when saving
+    charset_override: Option<Charset>,
+    /// The indentation detected in the open file, used instead of the settings
+    detected_indentation: Option<indent::Indentation>,
+    /// The EditorConfig properties of the open file
+    editorconfig: editorconfig::Properties,
+    /// The character set the open file was read in, used instead of the settings
+    charset: Charset,
+    /// The edits that can be undone and redone
+    history: History,
+    /// The cursor of the document that was dropped while the window is closed, to reload it when it opens
+    released_cursor: Option<(usize, usize)>,
+    /// The open prompt and the content of its input
+    prompt: Option<(Prompt, String)>,
+    /// The command palette, if it is open
+    palette: Option<Palette>,
+    /// The key bindings of the editor commands
+    keymap: Keymap,
+    /// The theme of the user interface, derived from the highlighter theme
+    app_theme: Theme,
+    /// The font of the editor
+    font: Font,
+    /// The current font size of the editor, changed by zooming
+    font_size: f32,
     /// The global hotkey manager
     _key_manager: GlobalHotKeyManager,
     /// The application settings
@@ -56,72 +194,582 @@ pub struct Editor {
 pub enum Message {
     /// An action was performed in the editor
     ActionPerformed(text_editor::Action),
+    /// Break the line at the cursor, indenting the new line
+    NewLine,
+    /// Indent the selected lines, or insert a tab at the cursor
+    Indent,
+    /// Remove a level of indentation from the selected lines
+    Outdent,
+    /// Convert the indentation of every line to spaces (`true`) or tabs
+    ConvertIndentation(bool),
+    /// Type a character that may open or close an auto-closing pair
+    TypePairCharacter(char),
+    /// Delete both characters of an empty pair around the cursor
+    DeletePair,
+    /// Move the cursor to the bracket matching the one next to it
+    JumpToBracket,
+    /// Run an operation on the selected lines
+    EditLines(lines::Operation),
+    /// Toggle a line comment, or a block comment (`true`), on the selection
+    ToggleComment(bool),
+    /// Undo the last edit
+    Undo,
+    /// Redo the last undone edit
+    Redo,
+    /// Type a closing bracket, removing a level of indentation at the start of a line
+    CloseBracket(char),
     /// Create a new file
     NewFile,
     /// Open a file
     OpenFile,
-    /// A file was opened
-    FileOpened(Result<(PathBuf, Arc<String>), Error>),
+    /// A file was opened, together with whether it can be written
+    FileOpened(Result<(PathBuf, Opened, bool), Error>),
+    /// Load the window of the large file starting at the given line
+    LoadLargeWindow(usize),
+    /// Load the page of the hex view that shows the given offset
+    LoadHexPage(u64),
+    /// A page of the hex view was read, with its offset
+    HexPageLoaded(Result<(u64, Arc<Vec<u8>>), Error>),
+    /// The mouse wheel was turned over the hex view
+    HexScrolled(mouse::ScrollDelta),
+    /// A key was pressed while the hex view is shown
+    HexKeyPressed(keyboard::Key, keyboard::Modifiers),
+    /// A window of the large file was read, with its first line and the line and column to move the cursor to
+    LargeWindowLoaded(Result<(usize, Arc<String>), Error>, Option<(usize, usize)>),
     /// Save the file
     SaveFile,
     /// A file was saved
-    FileSaved(Result<PathBuf, Error>),
+    FileSaved(Result<(PathBuf, editorconfig::Properties), Error>),
+    /// Allow or block editing the document
+    ToggleReadOnly,
+    /// Start or stop following the file as it grows
+    ToggleFollow,
+    /// The followed file changed
+    FileChanged(follow::Change),
     /// A hotkey was pressed
     HotkeyPressed(GlobalHotKeyEvent),
-    /// Initialize the window and set the window ID (only called once)
-    InitWindow(Option<iced::window::Id>),
+    /// Show or hide the window
+    ToggleWindow,
+    /// Hide the window if it is visible, saving the file first if configured
+    HideWindow,
+    /// The window lost the focus
+    WindowUnfocused,
+    /// Escape was pressed
+    EscapePressed,
+    /// The window was opened, restore its state
+    InitWindow(window::Id),
+    /// The window was closed to free its memory
+    WindowClosed,
+    /// The document dropped while the window was closed was read again
+    DocumentReloaded(Result<Document, Error>),
+    /// The window was moved to a new position
+    WindowMoved(Point),
+    /// The window was resized
+    WindowResized(Size),
+    /// The new size of the window, together with whether it is maximized
+    WindowMaximized(Size, bool),
+    /// The dropdown window was placed on its monitor, at the given top left corner of the monitor
+    DropdownPlaced(Option<Point>),
+    /// Move the sliding dropdown window to its position at the given time
+    DropdownFrame(Instant),
+    /// Save the window state, if it did not change since the given version
+    SaveWindowState(u64),
+    /// The user asked to close the window
+    WindowCloseRequested,
+    /// Open the "go to line" prompt
+    GoToLine,
+    /// Open the prompt to set the language of the document
+    SetLanguage,
+    /// Open the prompt to set the line ending the document is saved with
+    SetLineEnding,
+    /// Open the prompt to set the character set the document is saved in
+    SetCharset,
+    /// The input of the prompt changed
+    PromptChanged(String),
+    /// Apply the input of the prompt
+    PromptSubmitted,
+    /// Close the prompt without applying it
+    PromptClosed,
+    /// Open or close the command palette
+    CommandPalette,
+    /// The query of the command palette changed
+    PaletteQueryChanged(String),
+    /// Move the highlighted entry of the command palette
+    PaletteSelectionMoved(isize),
+    /// Run the highlighted command of the command palette
+    PaletteSubmitted,
+    /// Run a command picked from the command palette
+    PaletteCommandSelected(Command),
+    /// Close the command palette without running a command
+    PaletteClosed,
+    /// Turn word wrapping on or off
+    ToggleWordWrap,
+    /// Switch to the next highlighting theme
+    NextTheme,
+    /// The color scheme of the desktop changed
+    ColorSchemeChanged(ColorScheme),
+    /// A font from the fonts directory was loaded
+    FontLoaded(Result<(), font::Error>),
+    /// Change the font size by the given amount
+    Zoom(f32),
+    /// Reset the font size to the one of the settings
+    ZoomReset,
 }
 
 impl Editor {
     /// Create a new editor instance. WE regsiter the global hotkey manager, the settings module and the window ID here.
-    pub fn new() -> (Self, Task<Message>) {
+    pub fn new(app_settings: settings::Settings, window_state: WindowState, window_settings: window::Settings) -> (Self, Task<Message>) {
         // Registers hotkey for the app
         let hotkey_manager = GlobalHotKeyManager::new().expect("Failed to create hotkey manager");
         hotkey_manager.register(*START_KEY).expect("Failed to register hotkey");
 
-        // Load settings and get the default file path
-        let app_settings = settings::Settings::new().expect("Failed to load settings");
+        // Get the default file path
         let default_file = app_settings.startup_file_path.clone();
+        let large_file_threshold = app_settings.large_file_threshold;
+        let keymap = Keymap::new(&app_settings.keybindings);
+        let dropdown = (app_settings.window_mode == settings::WindowMode::Dropdown)
+            .then(|| Dropdown::new(app_settings.dropdown_edge, app_settings.dropdown_size));
+        let app_theme = app_settings.get_app_theme();
+        let font_size = app_settings.font_size;
+        let font = if app_settings.font_family.eq_ignore_ascii_case("monospace") {
+            Font::MONOSPACE
+        } else {
+            // Fonts are looked up by a static name, the settings live as long as the app anyway
+            Font::with_name(Box::leak(app_settings.font_family.clone().into_boxed_str()))
+        };
+        let load_fonts = Task::batch(
+            load_fonts(&settings::config_dir().join(FONTS_DIR_NAME))
+                .into_iter()
+                .map(|bytes| iced::font::load(bytes).map(Message::FontLoaded)),
+        );
+
+        let mut editor = Self {
+            window_id: None,
+            file: None,
+            document: Document::default(),
+            content: text_editor::Content::new(),
+            large_file: None,
+            hex_view: None,
+            is_loading: true,
+            is_dirty: false,
+            is_read_only: false,
+            is_writable: true,
+            is_following: false,
+            was_read_only: false,
+            is_visible: window_state.visible,
+            window_state,
+            window_settings,
+            window_state_version: 0,
+            dropdown,
+            language: language::PLAIN_TEXT.to_owned(),
+            language_override: None,
+            line_ending_override: None,
+            charset_override: None,
+            detected_indentation: None,
+            editorconfig: editorconfig::Properties::default(),
+            charset: Charset::default(),
+            history: History::default(),
+            released_cursor: None,
+            prompt: None,
+            palette: None,
+            keymap,
+            app_theme,
+            font,
+            font_size,
+            _key_manager: hotkey_manager,
+            _settings: app_settings,
+        };
+
+        // In the closing background mode a window that starts in the background is only opened by the hotkey
+        let open_window = if editor.is_visible || editor._settings.background_mode == settings::BackgroundMode::Hide {
+            editor.open_window()
+        } else {
+            Task::none()
+        };
 
         (
-            Self {
-                window_id: None,
-                file: None,
-                content: text_editor::Content::new(),
-                is_loading: true,
-                is_dirty: false,
-                is_visible: true,
-                _key_manager: hotkey_manager,
-                _settings: app_settings,
-            },
+            editor,
             Task::batch([
                 // Load the default file
                 Task::perform(
-                    load_file(default_file),
+                    load_file(default_file, large_file_threshold),
                     Message::FileOpened,
                 ),
-                // Get the window ID
-                iced::window::get_latest().map(Message::InitWindow),
-                widget::focus_next(),
-
+                open_window,
+                // Load the fonts of the user
+                load_fonts,
             ]),
         )
     }
 
-    /// Internal iced update cycle
+    /// Internal iced update cycle. The edits of a message are undone at once, except for typing.
     pub fn update(&mut self, message: Message) -> Task<Message> {
+        let is_command = !matches!(message, Message::ActionPerformed(_));
+
+        if is_command {
+            self.history.begin();
+        }
+        let task = self.handle(message);
+        if is_command {
+            self.history.end();
+        }
+
+        task
+    }
+
+    /// Handles a message
+    fn handle(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::ActionPerformed(action) => {
+                // Large files are only viewed, the editor holds just a window of them
+                if action.is_edit() && !self.is_editable() {
+                    return Task::none();
+                }
+
                 self.is_dirty = self.is_dirty || action.is_edit();
 
-                self.content.perform(action);
+                let is_edit = action.is_edit();
+                self.perform(action);
+
+                // Untitled documents have nothing but their first lines to guess the language from
+                if is_edit && self.file.is_none() && self.content.cursor_position().0 < language::HEAD_LINES {
+                    let head: Vec<String> = self
+                        .content
+                        .lines()
+                        .take(language::HEAD_LINES)
+                        .map(|line| line.to_string())
+                        .collect();
+                    self.language = language::detect(None, &head.join("\n"));
+                }
+
+                Task::none()
+            }
+            Message::NewLine => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                let (line, column) = self.content.cursor_position();
+                let text = self.content.line(line).map(|line| line.to_string()).unwrap_or_default();
+                let (before, after) = text.split_at(column.min(text.len()));
+                let openers = self._settings.indent_after(self.language());
+                let new_line = indent::new_line(before, after, self.language(), &openers, &self.indentation().unit());
+
+                self.perform(text_editor::Action::Edit(text_editor::Edit::Enter));
+                self.paste(new_line.indent);
+
+                // Between a pair of brackets the closing one goes to its own line
+                if let Some(closing_indent) = new_line.closing_indent {
+                    self.perform(text_editor::Action::Edit(text_editor::Edit::Enter));
+                    self.paste(closing_indent);
+                    self.content.perform(text_editor::Action::Move(text_editor::Motion::Up));
+                    self.content.perform(text_editor::Action::Move(text_editor::Motion::End));
+                }
+
+                self.is_dirty = true;
+                Task::none()
+            }
+            Message::Indent => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                let indentation = self.indentation();
+                let is_multiline = self.content.selection().is_some_and(|selection| selection.contains('\n'));
+
+                if is_multiline {
+                    let (first, last) = cursor::selected_lines(&self.content);
+                    let unit = indentation.unit();
+
+                    self.map_lines(first, last, |line| {
+                        if line.is_empty() {
+                            line.to_owned()
+                        } else {
+                            format!("{unit}{line}")
+                        }
+                    });
+                    cursor::select_lines(&mut self.content, first, last);
+                } else {
+                    let (line, index) = self.content.cursor_position();
+                    let text = self.content.line(line).map(|line| line.to_string()).unwrap_or_default();
+                    let column = indentation.width(&text[..index.min(text.len())]);
+
+                    self.paste(indentation.tab_at(column));
+                }
+
+                self.is_dirty = true;
+                Task::none()
+            }
+            Message::Outdent => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                let indentation = self.indentation();
+                let is_multiline = self.content.selection().is_some_and(|selection| selection.contains('\n'));
+                let (first, last) = cursor::selected_lines(&self.content);
+                let (line, index) = self.content.cursor_position();
+                let text = self.content.line(line).map(|line| line.to_string()).unwrap_or_default();
+                let column = text[..index.min(text.len())].chars().count();
+                let removed = indentation.outdent(&text);
+
+                if !self.map_lines(first, last, |line| line[indentation.outdent(line)..].to_owned()) {
+                    return Task::none();
+                }
+
+                if is_multiline {
+                    cursor::select_lines(&mut self.content, first, last);
+                } else {
+                    cursor::move_to(&mut self.content, line, column.saturating_sub(removed));
+                }
+
+                self.is_dirty = true;
+                Task::none()
+            }
+            Message::ConvertIndentation(insert_spaces) => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                let indentation = indent::Indentation {
+                    insert_spaces,
+                    ..self.indentation()
+                };
+                self.detected_indentation = Some(indentation);
+                log::info!("Converting the indentation to {:?}", indentation);
+
+                let (line, _) = self.content.cursor_position();
+                if self.map_lines(0, self.content.line_count().saturating_sub(1), |line| indentation.convert(line)) {
+                    cursor::move_to(&mut self.content, line, 0);
+                    self.is_dirty = true;
+                }
+
+                Task::none()
+            }
+            Message::TypePairCharacter(character) => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                let pairs = self._settings.auto_close_pairs(self.language());
+                let (line, index) = self.content.cursor_position();
+                let text = self.content.line(line).map(|line| line.to_string()).unwrap_or_default();
+                let index = index.min(text.len());
+                let before = text[..index].chars().next_back();
+                let after = text[index..].chars().next();
+                let selection = self.content.selection();
+
+                // Typing the closing character in front of itself steps over it
+                if selection.is_none() && after == Some(character) && pairs.iter().any(|(_, close)| *close == character) {
+                    self.content.perform(text_editor::Action::Move(text_editor::Motion::Right));
+                    return Task::none();
+                }
+
+                if let Some((open, close)) = pairs.iter().copied().find(|(open, _)| *open == character) {
+                    if let Some(selection) = selection {
+                        self.paste(format!("{open}{selection}{close}"));
+                        self.is_dirty = true;
+                        return Task::none();
+                    }
+
+                    if brackets::should_close(&pairs, open, close, before, after) {
+                        self.perform(text_editor::Action::Edit(text_editor::Edit::Insert(open)));
+                        self.perform(text_editor::Action::Edit(text_editor::Edit::Insert(close)));
+                        self.content.perform(text_editor::Action::Move(text_editor::Motion::Left));
+                        self.is_dirty = true;
+                        return Task::none();
+                    }
+                }
+
+                if indent::CLOSERS.contains(&character) && self._settings.auto_indent(self.language()) {
+                    return self.update(Message::CloseBracket(character));
+                }
 
+                self.perform(text_editor::Action::Edit(text_editor::Edit::Insert(character)));
+                self.is_dirty = true;
+                Task::none()
+            }
+            Message::DeletePair => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                self.perform(text_editor::Action::Edit(text_editor::Edit::Delete));
+                self.perform(text_editor::Action::Edit(text_editor::Edit::Backspace));
+                self.is_dirty = true;
+                Task::none()
+            }
+            Message::JumpToBracket => {
+                let (line, index) = self.content.cursor_position();
+
+                if let Some((_, (partner_line, partner_index))) = brackets::find_match(&self.content, line, index) {
+                    let column = self
+                        .content
+                        .line(partner_line)
+                        .map_or(0, |text| text[..partner_index].chars().count());
+                    cursor::move_to(&mut self.content, partner_line, column);
+                }
+
+                Task::none()
+            }
+            Message::EditLines(operation) => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                let selection = self.content.selection();
+                let is_multiline = selection.as_deref().is_some_and(|selection| selection.contains('\n'));
+                let (line, index) = self.content.cursor_position();
+                let column = self.content.line(line).map_or(0, |text| text[..index.min(text.len())].chars().count());
+                let line_count = self.content.line_count();
+                let (mut first, mut last) = cursor::selected_lines(&self.content);
+
+                if operation.applies_to_document() && first == last {
+                    (first, last) = (0, line_count - 1);
+                }
+                let selected = self.lines(first, last);
+                let count = selected.len();
+
+                // Where the cursor goes after the edit, and the lines that stay selected
+                let (changed, cursor_line, cursor_column, selected_lines) = match operation {
+                    Operation::Duplicate => {
+                        // A selection inside a line is duplicated on its own
+                        if let Some(selection) = selection.filter(|selection| !is_multiline && !selection.is_empty()) {
+                            self.paste(selection.repeat(2));
+                            self.is_dirty = true;
+                            return Task::none();
+                        }
+
+                        let changed = self.set_lines(first, last, [selected.clone(), selected].concat());
+                        (changed, line + count, column, (first + count, last + count))
+                    }
+                    Operation::MoveUp => {
+                        let Some(above) = first.checked_sub(1) else {
+                            return Task::none();
+                        };
+
+                        let moved = [selected, self.lines(above, above)].concat();
+                        (self.set_lines(above, last, moved), line - 1, column, (first - 1, last - 1))
+                    }
+                    Operation::MoveDown => {
+                        if last + 1 >= line_count {
+                            return Task::none();
+                        }
+
+                        let moved = [self.lines(last + 1, last + 1), selected].concat();
+                        (self.set_lines(first, last + 1, moved), line + 1, column, (first + 1, last + 1))
+                    }
+                    Operation::Delete => (self.set_lines(first, last, Vec::new()), first, column, (first, first)),
+                    Operation::Join => {
+                        let last = if first == last { (last + 1).min(line_count - 1) } else { last };
+                        let joined = self.lines(first, last);
+                        let junction = joined[0].chars().count();
+
+                        (self.set_lines(first, last, vec![lines::join(&joined)]), first, junction, (first, first))
+                    }
+                    Operation::Sort(order) => {
+                        (self.set_lines(first, last, lines::sort(selected, order)), line, column, (first, last))
+                    }
+                    Operation::Unique => {
+                        let unique = lines::unique(selected);
+                        let kept = unique.len();
+                        (self.set_lines(first, last, unique), line, column, (first, first + kept - 1))
+                    }
+                    Operation::Reverse => {
+                        let reversed = selected.into_iter().rev().collect();
+                        (self.set_lines(first, last, reversed), line, column, (first, last))
+                    }
+                };
+
+                if !changed {
+                    return Task::none();
+                }
+
+                if is_multiline && !matches!(operation, Operation::Delete | Operation::Join) {
+                    cursor::select_lines(&mut self.content, selected_lines.0, selected_lines.1);
+                } else {
+                    cursor::move_to(&mut self.content, cursor_line, cursor_column);
+                }
+
+                self.is_dirty = true;
+                Task::none()
+            }
+            Message::ToggleComment(block) => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                let tokens = self._settings.comment_tokens(self.language());
+
+                match comment::toggle(&self.content, &tokens, block) {
+                    None => {
+                        log::warn!("No comment tokens for the language {:?}", self.language());
+                        return Task::none();
+                    }
+                    Some(comment::Toggle::Selection(text)) => self.paste(text),
+                    Some(comment::Toggle::Lines { first, last, lines: toggled, cursor: position }) => {
+                        if !self.set_lines(first, last, toggled) {
+                            return Task::none();
+                        }
+
+                        match position {
+                            Some((line, column)) => cursor::move_to(&mut self.content, line, column),
+                            None => cursor::select_lines(&mut self.content, first, last),
+                        }
+                    }
+                }
+
+                self.is_dirty = true;
+                Task::none()
+            }
+            Message::Undo => {
+                if let Some(step) = self.is_editable().then(|| self.history.undo()).flatten() {
+                    self.replay(step, true);
+                }
+
+                Task::none()
+            }
+            Message::Redo => {
+                if let Some(step) = self.is_editable().then(|| self.history.redo()).flatten() {
+                    self.replay(step, false);
+                }
+
+                Task::none()
+            }
+            Message::CloseBracket(bracket) => {
+                if !self.is_editable() {
+                    return Task::none();
+                }
+
+                let (line, column) = self.content.cursor_position();
+                let text = self.content.line(line).map(|line| line.to_string()).unwrap_or_default();
+
+                for _ in 0..indent::dedent(&text[..column.min(text.len())], &self.indentation().unit()) {
+                    self.perform(text_editor::Action::Edit(text_editor::Edit::Backspace));
+                }
+                self.perform(text_editor::Action::Edit(text_editor::Edit::Insert(bracket)));
+
+                self.is_dirty = true;
                 Task::none()
             }
             Message::NewFile => {
                 if !self.is_loading {
                     self.file = None;
+                    self.document = Document::default();
                     self.content = text_editor::Content::new();
+                    self.history = History::default();
+                    self.large_file = None;
+                    self.hex_view = None;
+                    self.is_read_only = false;
+                    self.is_writable = true;
+                    self.is_following = false;
+                    self.language = language::PLAIN_TEXT.to_owned();
+                    self.language_override = None;
+                    self.line_ending_override = None;
+                    self.charset_override = None;
+                    self.detected_indentation = None;
+                    self.editorconfig = editorconfig::Properties::default();
+                    self.charset = Charset::default();
                 }
 
                 Task::none()
@@ -132,69 +780,619 @@ impl Editor {
                 } else {
                     self.is_loading = true;
 
-                    Task::perform(open_file(), Message::FileOpened)
+                    Task::perform(open_file(self._settings.large_file_threshold), Message::FileOpened)
                 }
             }
             Message::FileOpened(result) => {
                 self.is_loading = false;
                 self.is_dirty = false;
 
-                if let Ok((path, contents)) = result {
-                    self.file = Some(path);
-                    self.content = text_editor::Content::with_text(&contents);
+                if let Ok((_, _, is_writable)) = &result {
+                    self.is_writable = *is_writable;
+                    self.is_read_only = !is_writable;
+                    self.is_following = false;
+                }
+
+                match result {
+                    Ok((path, Opened::Document(document, charset, editorconfig), _)) => {
+                        self.language = language::detect(Some(&path), &document.head_and_tail(language::HEAD_LINES));
+                        self.language_override = None;
+                        self.line_ending_override = None;
+                        self.charset_override = None;
+                        self.file = Some(path);
+                        self.editorconfig = editorconfig;
+                        self.charset = charset;
+                        self.detected_indentation = indent::detect(
+                            &document.head_and_tail(indent::DETECT_LINES),
+                            self._settings.indentation(&self.language).tab_width,
+                        );
+                        log::info!("Detected indentation: {:?}", self.detected_indentation);
+                        self.content = document.to_content();
+                        self.document = document;
+                        self.history = History::default();
+                        self.large_file = None;
+                        self.hex_view = None;
+                    }
+                    Ok((path, Opened::Large(large_file), _)) => {
+                        log::info!("Opening {:?} in large file mode", path);
+                        self.language = language::detect(Some(&path), "");
+                        self.language_override = None;
+                        self.line_ending_override = None;
+                        self.charset_override = None;
+                        self.detected_indentation = None;
+                        self.editorconfig = editorconfig::Properties::default();
+                        self.charset = Charset::default();
+                        self.file = Some(path);
+                        self.content = text_editor::Content::new();
+                        self.document = Document::default();
+                        self.history = History::default();
+                        self.large_file = Some(large_file);
+                        self.hex_view = None;
+
+                        return self.update(Message::LoadLargeWindow(0));
+                    }
+                    Ok((path, Opened::Binary(hex_view), _)) => {
+                        log::info!("Opening {:?} in the hex view", path);
+                        self.language = language::PLAIN_TEXT.to_owned();
+                        self.language_override = None;
+                        self.line_ending_override = None;
+                        self.charset_override = None;
+                        self.detected_indentation = None;
+                        self.editorconfig = editorconfig::Properties::default();
+                        self.charset = Charset::default();
+                        self.file = Some(path);
+                        self.content = text_editor::Content::new();
+                        self.document = Document::default();
+                        self.history = History::default();
+                        self.large_file = None;
+                        self.hex_view = Some(hex_view);
+                        self.is_read_only = true;
+                    }
+                    Err(error) => log::error!("Failed to open file: {:?}", error),
+                }
+
+                Task::none()
+            }
+            Message::LoadHexPage(offset) => {
+                let Some(hex_view) = self.hex_view.clone() else {
+                    return Task::none();
+                };
+
+                Task::perform(async move { hex_view.read_page(offset).await }, |result| {
+                    Message::HexPageLoaded(result.map_err(|error| Error::IoError(error.kind())))
+                })
+            }
+            Message::HexPageLoaded(result) => {
+                match (result, &mut self.hex_view) {
+                    (Ok((offset, bytes)), Some(hex_view)) => hex_view.set_page(offset, bytes),
+                    (Err(error), _) => log::error!("Failed to read the binary file: {:?}", error),
+                    _ => {}
+                }
+                Task::none()
+            }
+            Message::HexScrolled(delta) => {
+                let rows = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => -y * 3.0,
+                    mouse::ScrollDelta::Pixels { y, .. } => -y / 20.0,
+                };
+
+                self.scroll_hex_view(rows.round() as i64)
+            }
+            Message::HexKeyPressed(key, modifiers) => {
+                use keyboard::key::Named;
+
+                let page = hex::ROWS_PER_PAGE as i64;
+
+                match key.as_ref() {
+                    keyboard::Key::Named(Named::ArrowDown) => self.scroll_hex_view(1),
+                    keyboard::Key::Named(Named::ArrowUp) => self.scroll_hex_view(-1),
+                    keyboard::Key::Named(Named::PageDown) => self.scroll_hex_view(page),
+                    keyboard::Key::Named(Named::PageUp) => self.scroll_hex_view(-page),
+                    keyboard::Key::Named(Named::Home) => self.update(Message::LoadHexPage(0)),
+                    keyboard::Key::Named(Named::End) => self.update(Message::LoadHexPage(u64::MAX)),
+                    // The text editor is not shown, so its key bindings are run from here
+                    _ => {
+                        let key_press = text_editor::KeyPress {
+                            key,
+                            modifiers,
+                            text: None,
+                            status: text_editor::Status::Focused,
+                        };
+
+                        match self.keymap.command(&key_press) {
+                            Some(command) => self.update(command.message()),
+                            None => Task::none(),
+                        }
+                    }
+                }
+            }
+            Message::LoadLargeWindow(first_line) => self.load_large_window(first_line, None),
+            Message::LargeWindowLoaded(result, cursor) => {
+                self.is_loading = false;
+
+                match (result, &mut self.large_file) {
+                    (Ok((first_line, text)), Some(large_file)) => {
+                        large_file.set_first_line(first_line);
+                        self.content = text_editor::Content::with_text(&text);
+
+                        if let Some((line, column)) = cursor {
+                            cursor::move_to(&mut self.content, line - first_line, column);
+                        }
+                    }
+                    (Err(error), _) => log::error!("Failed to read the large file: {:?}", error),
+                    _ => {}
                 }
 
                 Task::none()
             }
             Message::SaveFile => {
-                if self.is_loading {
+                if self.is_loading || self.large_file.is_some() || self.hex_view.is_some() {
                     Task::none()
                 } else {
                     self.is_loading = true;
 
+                    if self.is_editable() {
+                        self.transform_before_save();
+                    }
+
+                    // Files that can't be written are saved under a new path
+                    let path = self.file.clone().filter(|_| self.is_writable);
+                    let format = self.default_format();
+                    let overrides = (self.line_ending_override, self.charset_override);
+
                     Task::perform(
-                        save_file(self.file.clone(), self.content.text()),
+                        save_file(path, self.document.clone(), format, overrides),
                         Message::FileSaved,
                     )
                 }
             }
-            Message::FileSaved(result) => {
-                self.is_loading = false;
-
-                if let Ok(path) = result {
-                    self.file = Some(path);
-                    self.is_dirty = false;
+            Message::FileSaved(result) => {
+                self.is_loading = false;
+
+                match result {
+                    Ok((path, editorconfig)) => {
+                        self.editorconfig = editorconfig;
+
+                        // A file saved for the first time may now be recognized by its name
+                        if self.file.as_ref() != Some(&path) {
+                            self.language = language::detect(Some(&path), &self.document.head_and_tail(language::HEAD_LINES));
+                        }
+
+                        self.file = Some(path);
+                        self.is_dirty = false;
+                        self.is_writable = true;
+                    }
+                    Err(Error::IoError(io::ErrorKind::PermissionDenied)) if self.is_writable => {
+                        log::warn!("The file can't be written, asking for a new path");
+                        self.is_writable = false;
+                        return self.update(Message::SaveFile);
+                    }
+                    Err(error) => log::error!("Failed to save file: {:?}", error),
+                }
+
+                Task::none()
+            }
+            Message::ToggleReadOnly => {
+                self.is_read_only = !self.is_read_only;
+                log::info!("Read-only: {}", self.is_read_only);
+                Task::none()
+            }
+            Message::ToggleFollow => {
+                if self.file.is_none() || self.large_file.is_some() || self.hex_view.is_some() {
+                    log::warn!("Only files that are loaded at once can be followed");
+                    return Task::none();
+                }
+
+                // The followed file is the source of truth, unsaved edits would be lost when it is read
+                if !self.is_following && self.is_dirty {
+                    log::warn!("Save the file before following it");
+                    return Task::none();
+                }
+
+                self.is_following = !self.is_following;
+                log::info!("Following: {}", self.is_following);
+
+                // The history is cleared once the file is read
+                if self.is_following {
+                    self.was_read_only = self.is_read_only;
+                    self.is_read_only = true;
+                } else {
+                    self.is_read_only = self.was_read_only;
+                }
+                Task::none()
+            }
+            Message::FileChanged(change) => {
+                if !self.is_following {
+                    return Task::none();
+                }
+
+                match change {
+                    follow::Change::Reloaded(document) => {
+                        self.content = document.to_content();
+                        self.content.perform(text_editor::Action::Move(text_editor::Motion::DocumentEnd));
+                        self.document = document;
+                        self.history = History::default();
+                        self.is_dirty = false;
+                    }
+                    follow::Change::Appended(text) => {
+                        // Keep scrolling along, unless the cursor was moved away from the last line. The text editor
+                        // has no scroll offset to read, so scrolling up without moving the cursor still scrolls along.
+                        let (line, column) = self.content.cursor_position();
+                        let at_end = line + 1 == self.content.line_count();
+
+                        self.content.perform(text_editor::Action::Move(text_editor::Motion::DocumentEnd));
+                        self.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text))));
+
+                        if !at_end {
+                            cursor::move_to(&mut self.content, line, column);
+                        }
+                    }
+                }
+
+                Task::none()
+            }
+            Message::HotkeyPressed(hotkey) => {
+                if hotkey.state == HotKeyState::Released {
+                    if hotkey.id == START_KEY.id {
+                        return self.update(Message::ToggleWindow);
+                    } else {
+                        log::info!("Unknown hotkey event: {:?}", hotkey);
+                    }
+                }
+                Task::none()
+            }
+            Message::ToggleWindow => {
+                // The window opens once the dropped document is read again
+                if self.released_cursor.is_some() && self.is_loading {
+                    return Task::none();
+                }
+
+                self.is_visible = !self.is_visible;
+                self.window_state.visible = self.is_visible;
+                let save = self.save_window_state();
+
+                let Some(window_id) = self.window_id else {
+                    // The window was closed in the background, open a new one once the document is back
+                    if let (Some(path), Some(_)) = (self.file.clone(), self.released_cursor) {
+                        log::info!("Reloading {:?}", path);
+                        self.is_loading = true;
+                        let load = Document::load(path, Some(self.format().charset));
+
+                        return Task::perform(load, |result| {
+                            Message::DocumentReloaded(result.map(|(document, _)| document).map_err(|error| Error::IoError(error.kind())))
+                        })
+                        .chain(save);
+                    }
+
+                    log::info!("Opening window");
+                    return self.open_window().chain(save);
+                };
+
+                // Only a new window is told the size of its monitor, so the dropdown window is opened again,
+                // then slides in once it is placed
+                if self.dropdown.is_some() && self.is_visible {
+                    log::info!("Opening dropdown window");
+                    return window::close(window_id).chain(self.open_window()).chain(save);
+                }
+
+                // The dropdown window slides out, unless the size of the screen is unknown
+                if let Some(dropdown) = self.dropdown.as_mut().filter(|dropdown| dropdown.window_size().is_some()) {
+                    log::info!("Sliding window out");
+                    dropdown.start_slide(false);
+                    return save;
+                }
+
+                if self.is_visible {
+                    log::info!("Showing window");
+                    iced::window::change_mode(window_id, window::Mode::Windowed).chain(save)
+                } else {
+                    self.release_window(window_id).chain(save)
+                }
+            }
+            Message::HideWindow => {
+                if !self.is_visible {
+                    return Task::none();
+                }
+
+                // Untitled files are not saved, that would need a dialog
+                let save = if self._settings.save_on_hide && self.is_dirty && self.file.is_some() {
+                    self.update(Message::SaveFile)
+                } else {
+                    Task::none()
+                };
+
+                save.chain(self.update(Message::ToggleWindow))
+            }
+            Message::WindowUnfocused => {
+                // The file dialogs take the focus while a file is loading or saving
+                if self._settings.hide_on_blur && !self.is_loading {
+                    return self.update(Message::HideWindow);
+                }
+                Task::none()
+            }
+            Message::EscapePressed => {
+                // Escape closes the prompt, then the command palette, and only then hides the window
+                if self.prompt.is_some() {
+                    self.update(Message::PromptClosed)
+                } else if self.palette.is_some() {
+                    self.update(Message::PaletteClosed)
+                } else if self._settings.escape_hides {
+                    self.update(Message::HideWindow)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::InitWindow(id) => {
+                if self.dropdown.is_some() {
+                    window::get_position(id).map(Message::DropdownPlaced)
+                } else if self.window_state.maximized {
+                    window::maximize(id, true)
+                } else {
+                    Task::none()
+                }
+            }
+            Message::WindowClosed => {
+                log_memory_usage("after closing the window");
+                Task::none()
+            }
+            Message::DocumentReloaded(result) => {
+                self.is_loading = false;
+                let cursor = self.released_cursor.take();
+
+                match result {
+                    Ok(document) => {
+                        self.content = document.to_content();
+                        self.document = document;
+
+                        if let Some((line, column)) = cursor {
+                            cursor::move_to(&mut self.content, line, column);
+                        }
+                    }
+                    // The file is left empty, as if it was removed while the window was closed
+                    Err(error) => log::error!("Failed to reload the file: {:?}", error),
+                }
+
+                log::info!("Opening window");
+                self.open_window()
+            }
+            Message::DropdownPlaced(origin) => {
+                let (Some(id), Some(dropdown)) = (self.window_id, &mut self.dropdown) else {
+                    return Task::none();
+                };
+
+                if let Some(origin) = origin {
+                    dropdown.set_origin(origin);
+                }
+
+                // The window is placed outside of the screen, and slides in if it is shown
+                match (dropdown.window_size(), dropdown.hidden_position()) {
+                    (Some(size), Some(position)) => {
+                        if self.is_visible {
+                            log::info!("Sliding window in");
+                            dropdown.start_slide(true);
+                        }
+                        window::resize(id, size).chain(window::move_to(id, position))
+                    }
+                    _ => {
+                        log::warn!("The size of the screen is unknown, the dropdown window can't be placed");
+                        Task::none()
+                    }
+                }
+            }
+            Message::DropdownFrame(now) => {
+                let (Some(id), Some(dropdown)) = (self.window_id, &mut self.dropdown) else {
+                    return Task::none();
+                };
+
+                match dropdown.step(now) {
+                    Some((position, true)) if !self.is_visible => {
+                        window::move_to(id, position).chain(self.release_window(id))
+                    }
+                    Some((position, _)) => window::move_to(id, position),
+                    None => Task::none(),
+                }
+            }
+            Message::WindowMoved(position) => {
+                // A maximized window keeps the position it is restored to,
+                // the dropdown window is always placed on its edge
+                if self.window_state.maximized || self.dropdown.is_some() {
+                    return Task::none();
+                }
+
+                self.window_state.position = Some((position.x, position.y));
+                self.save_window_state()
+            }
+            Message::WindowResized(size) => match self.window_id {
+                Some(id) if self.dropdown.is_none() => window::get_maximized(id).map(move |maximized| Message::WindowMaximized(size, maximized)),
+                _ => Task::none(),
+            },
+            Message::WindowMaximized(size, maximized) => {
+                self.window_state.maximized = maximized;
+
+                if !maximized {
+                    self.window_state.width = size.width;
+                    self.window_state.height = size.height;
+                }
+
+                self.save_window_state()
+            }
+            Message::SaveWindowState(version) => {
+                if version == self.window_state_version {
+                    log::debug!("Saving window state: {:?}", self.window_state);
+
+                    if let Err(error) = self.window_state.save() {
+                        log::error!("{}", error);
+                    }
+                }
+                Task::none()
+            }
+            Message::WindowCloseRequested => {
+                if let Err(error) = self.window_state.save() {
+                    log::error!("{}", error);
+                }
+
+                // Closing the window from its title bar quits, the background modes only apply to hiding it
+                iced::exit()
+            }
+            Message::GoToLine => {
+                let prompt = if self.hex_view.is_some() { Prompt::GoToOffset } else { Prompt::GoToLine };
+                self.prompt = Some((prompt, String::new()));
+                text_input::focus(PROMPT_INPUT_ID)
+            }
+            Message::SetLanguage => {
+                self.prompt = Some((Prompt::SetLanguage, self.language_override.clone().unwrap_or_default()));
+                text_input::focus(PROMPT_INPUT_ID)
+            }
+            Message::SetLineEnding => {
+                let input = self.line_ending_override.map(LineEnding::name).unwrap_or_default();
+                self.prompt = Some((Prompt::SetLineEnding, input.to_owned()));
+                text_input::focus(PROMPT_INPUT_ID)
+            }
+            Message::SetCharset => {
+                let input = self.charset_override.map(Charset::name).unwrap_or_default();
+                self.prompt = Some((Prompt::SetCharset, input.to_owned()));
+                text_input::focus(PROMPT_INPUT_ID)
+            }
+            Message::PromptChanged(input) => {
+                if let Some((_, value)) = &mut self.prompt {
+                    *value = input;
+                }
+                Task::none()
+            }
+            Message::PromptSubmitted => {
+                let Some((prompt, input)) = self.prompt.take() else {
+                    return Task::none();
+                };
+
+                match prompt {
+                    Prompt::GoToLine => {
+                        let first_line = self.large_file.as_ref().map_or(0, LargeFile::first_line);
+                        let current_line = first_line + self.content.cursor_position().0;
+
+                        if let Some(target) = goto::parse(&input, current_line) {
+                            log::info!("Going to {:?}", target);
+                            let column = target.column.unwrap_or(0);
+
+                            // Lines outside the window of a large file are loaded first
+                            if self.large_file.is_some() && !(first_line..first_line + self.content.line_count()).contains(&target.line) {
+                                let window_start = target.line.saturating_sub(large_file::WINDOW_LINES / 2);
+                                return self.load_large_window(window_start, Some((target.line, column)));
+                            }
+
+                            cursor::move_to(&mut self.content, target.line - first_line, column);
+                        }
+                    }
+                    Prompt::GoToOffset => {
+                        if let Some(offset) = hex::parse_offset(&input) {
+                            log::info!("Going to offset {:#x}", offset);
+                            return self.update(Message::LoadHexPage(offset));
+                        }
+                    }
+                    Prompt::SetLanguage => {
+                        let input = input.trim();
+                        log::info!("Setting language to: {:?}", input);
+                        self.language_override = (!input.is_empty()).then(|| input.to_owned());
+                    }
+                    Prompt::SetLineEnding => match input.trim() {
+                        "" => self.line_ending_override = None,
+                        input => match LineEnding::parse(input) {
+                            Some(line_ending) => {
+                                log::info!("Setting line ending to: {:?}", line_ending);
+                                self.line_ending_override = Some(line_ending);
+                            }
+                            None => log::warn!("Unknown line ending: {:?}", input),
+                        },
+                    },
+                    Prompt::SetCharset => match input.trim() {
+                        "" => self.charset_override = None,
+                        input => match Charset::parse(input) {
+                            Some(charset) => {
+                                log::info!("Setting charset to: {:?}", charset);
+                                self.charset_override = Some(charset);
+                            }
+                            None => log::warn!("Unknown charset: {:?}", input),
+                        },
+                    },
+                }
+
+                widget::focus_next()
+            }
+            Message::PromptClosed => {
+                self.prompt = None;
+                widget::focus_next()
+            }
+            Message::CommandPalette => {
+                if self.palette.is_some() {
+                    return self.update(Message::PaletteClosed);
+                }
+
+                self.palette = Some(Palette::default());
+                text_input::focus(PALETTE_INPUT_ID)
+            }
+            Message::PaletteQueryChanged(query) => {
+                if let Some(palette) = &mut self.palette {
+                    palette.query = query;
+                    palette.selected = 0;
+                }
+                Task::none()
+            }
+            Message::PaletteSelectionMoved(offset) => {
+                if let Some(palette) = &mut self.palette {
+                    palette.move_selection(offset);
+                }
+                Task::none()
+            }
+            Message::PaletteSubmitted => {
+                match self.palette.as_ref().and_then(Palette::selected) {
+                    Some(command) => self.update(Message::PaletteCommandSelected(command)),
+                    None => Task::none(),
                 }
+            }
+            Message::PaletteCommandSelected(command) => {
+                log::info!("Running command from palette: {}", command);
+                self.palette = None;
 
+                // Give the focus back to the editor before running the command,
+                // so commands that open an input can take it over
+                widget::focus_next().chain(self.update(command.message()))
+            }
+            Message::PaletteClosed => {
+                self.palette = None;
+                widget::focus_next()
+            }
+            Message::ToggleWordWrap => {
+                self._settings.word_wrap = !self._settings.word_wrap;
                 Task::none()
             }
-            Message::HotkeyPressed(hotkey) => {
-                if hotkey.state == HotKeyState::Released {
-                    let window_id = self.window_id.expect("Window ID not set");
-                    if hotkey.id == START_KEY.id {
-                        self.is_visible = !self.is_visible;
-                        if self.is_visible {
-                            log::info!("Showing window");
-                            return iced::window::change_mode(window_id, window::Mode::Windowed);
-                        } else {
-                            log::info!("Hiding window");
-                            return iced::window::change_mode(window_id, window::Mode::Hidden);
-                        }
-                    } else {
-                        log::info!("Unknown hotkey event: {:?}", hotkey);
-                    }
+            Message::NextTheme => {
+                self._settings.next_theme();
+                self.app_theme = self._settings.get_app_theme();
+                Task::none()
+            }
+            Message::ColorSchemeChanged(color_scheme) => {
+                self._settings.set_color_scheme(color_scheme);
+                self.app_theme = self._settings.get_app_theme();
+                Task::none()
+            }
+            Message::FontLoaded(result) => {
+                if let Err(error) = result {
+                    log::error!("Failed to load font: {:?}", error);
                 }
                 Task::none()
             }
-            Message::InitWindow(id) => {
-                self.window_id = id;
+            Message::Zoom(step) => {
+                self.set_font_size(self.font_size + step);
+                Task::none()
+            }
+            Message::ZoomReset => {
+                self.set_font_size(self._settings.font_size);
                 Task::none()
             }
         }
     }
 
     /// Internal iced view cycle
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self, _window: window::Id) -> Element<'_, Message> {
         let controls = row![
             action(new_icon(), "New file", Some(Message::NewFile)),
             action(
@@ -225,113 +1423,671 @@ impl Editor {
                 String::from("New file")
             }),
             horizontal_space(),
-            text({
-                let (line, column) = self.content.cursor_position();
-
-                format!("{}:{}", line + 1, column + 1)
-            })
         ]
+        // Clicking the lock allows editing again
+        .push_maybe(self.is_following.then(|| text("Following")))
+        .push_maybe(self.is_read_only.then(|| {
+            button(text("Read-only"))
+                .style(button::text)
+                .padding(0)
+                .on_press_maybe((self.large_file.is_none() && self.hex_view.is_none()).then_some(Message::ToggleReadOnly))
+        }))
+        .push_maybe((self.hex_view.is_none() && self.large_file.is_none()).then(|| {
+            let indentation = self.indentation();
+            text(if indentation.insert_spaces {
+                format!("Spaces: {}", indentation.tab_width)
+            } else {
+                format!("Tab width: {}", indentation.tab_width)
+            })
+        }))
+        .push_maybe((self.hex_view.is_none() && self.large_file.is_none()).then(|| {
+            let format = self.format();
+            text(format!("{} {}", format.line_ending.name().to_uppercase(), format.charset.name().to_uppercase()))
+        }))
+        .push(text(if self.hex_view.is_some() { "Binary" } else { self.language() }))
+        .push(text(if let Some(hex_view) = &self.hex_view {
+            format!("{:#x} of {:#x}", hex_view.offset(), hex_view.size())
+        } else {
+            let (line, column) = self.content.cursor_position();
+            let line = line + self.large_file.as_ref().map_or(0, LargeFile::first_line);
+
+            format!("{}:{}", line + 1, column + 1)
+        }))
         .spacing(10);
 
-        column![
-            controls,
-            text_editor(&self.content)
-                .height(Fill)
-                .on_action(Message::ActionPerformed)
-                .wrapping(if self._settings.word_wrap {
-                    text::Wrapping::Word
-                } else {
-                    text::Wrapping::None
-                })
-                .highlight(
-                    self.file
-                        .as_deref()
-                        .and_then(Path::extension)
-                        .and_then(ffi::OsStr::to_str)
-                        .unwrap_or("txt"),
-                    self._settings.get_theme(),
-                )
-                .key_binding(|key_press| {
-                    match key_press.key.as_ref() {
-                        keyboard::Key::Character("s")
-                            if key_press.modifiers.command() =>
-                        {
-                            log::info!("Save file");
-                            Some(text_editor::Binding::Custom(
-                                Message::SaveFile,
-                            ))
-                        }
-                        keyboard::Key::Character("o")
-                            if key_press.modifiers.command() =>
-                        {
-                            log::info!("Open file");
-                            Some(text_editor::Binding::Custom(
-                                Message::OpenFile,
-                            ))
-                        }
-                        keyboard::Key::Character("n")
-                            if key_press.modifiers.command() =>
-                        {
-                            log::info!("New file");
-                            Some(text_editor::Binding::Custom(
-                                Message::NewFile,
-                            ))
+        let prompt = self.prompt.as_ref().map(|(prompt, input)| {
+            row![
+                text(prompt.label()),
+                text_input(prompt.placeholder(), input)
+                    .id(PROMPT_INPUT_ID)
+                    .on_input(Message::PromptChanged)
+                    .on_submit(Message::PromptSubmitted),
+                button("Cancel")
+                    .style(button::secondary)
+                    .on_press(Message::PromptClosed),
+            ]
+            .spacing(10)
+            .align_y(Center)
+        });
+
+        let editor = text_editor(&self.content)
+            .height(Fill)
+            .font(self.font)
+            .size(self.font_size)
+            .line_height(text::LineHeight::Relative(self._settings.line_height))
+            .on_action(Message::ActionPerformed)
+            .wrapping(if self._settings.word_wrap && self.large_file.is_none() {
+                text::Wrapping::Word
+            } else {
+                text::Wrapping::None
+            })
+            .key_binding(|key_press| {
+                if let Some(command) = self.keymap.command(&key_press) {
+                    log::info!("Running command: {}", command);
+                    return Some(text_editor::Binding::Custom(command.message()));
+                }
+
+                if key_press.status == text_editor::Status::Focused {
+                    if let Some(message) = tab_binding(&key_press) {
+                        return Some(text_editor::Binding::Custom(message));
+                    }
+
+                    if let Some(message) = self.pair_binding(&key_press) {
+                        return Some(text_editor::Binding::Custom(message));
+                    }
+
+                    if self._settings.auto_indent(self.language()) {
+                        if let Some(message) = indent_binding(&key_press) {
+                            return Some(text_editor::Binding::Custom(message));
                         }
-                        _ => text_editor::Binding::from_key_press(key_press),
                     }
-                }),
-            status,
+                }
+
+                text_editor::Binding::from_key_press(key_press)
+            });
+
+        // Large files are not highlighted, binary files are shown in the hex view
+        let editor: Element<'_, Message> = if let Some(hex_view) = &self.hex_view {
+            mouse_area(
+                container(text(hex_view.rows()).font(self.font).size(self.font_size))
+                    .width(Fill)
+                    .height(Fill)
+                    .padding(5),
+            )
+            .on_scroll(Message::HexScrolled)
+            .into()
+        } else if self.large_file.is_some() {
+            editor.into()
+        } else {
+            editor
+                .highlight_with::<Highlighter>(
+                    highlighter::Settings {
+                        theme: self._settings.get_theme(),
+                        token: self.language().to_owned(),
+                        font: self.font,
+                        brackets: self.matching_brackets(),
+                    },
+                    |highlight, _theme| highlight.to_format(),
+                )
+                .into()
+        };
+
+        let content = column![controls]
+            .push_maybe(self.large_file.as_ref().map(|large_file| self.large_file_banner(large_file)))
+            .push_maybe(prompt)
+            .push(editor)
+            .push(status)
+            .spacing(10)
+            .padding(10);
+
+        if let Some(palette) = &self.palette {
+            stack![content, self.palette_view(palette)].into()
+        } else {
+            content.into()
+        }
+    }
+
+    /// Opens the window with the saved state applied, restoring the rest of its state once it is open
+    fn open_window(&mut self) -> Task<Message> {
+        let mut settings = self.window_state.apply(self.window_settings.clone());
+
+        if self.dropdown.is_some() {
+            settings = dropdown::apply(settings);
+        }
+
+        let (id, opened) = window::open(settings);
+        self.window_id = Some(id);
+
+        opened.map(Message::InitWindow).chain(widget::focus_next())
+    }
+
+    /// Puts the window in the background, either by hiding it or by closing it to free its memory
+    fn release_window(&mut self, id: window::Id) -> Task<Message> {
+        match self._settings.background_mode {
+            settings::BackgroundMode::Hide => {
+                log::info!("Hiding window");
+                window::change_mode(id, window::Mode::Hidden)
+            }
+            settings::BackgroundMode::Close => {
+                log::info!("Closing window");
+                log_memory_usage("before closing the window");
+                self.window_id = None;
+
+                // A saved document is read again when the window opens, unsaved changes and followed files are kept
+                let is_document = self.large_file.is_none() && self.hex_view.is_none();
+                if self.file.is_some() && is_document && !self.is_dirty && !self.is_following {
+                    self.released_cursor = Some(self.content.cursor_position());
+                    self.content = text_editor::Content::new();
+                    self.document = Document::default();
+                    self.history = History::default();
+                }
+
+                window::close(id)
+            }
+        }
+    }
+
+    /// Reads a window of the large file, moving the cursor to the given line and column once it is loaded
+    fn load_large_window(&mut self, first_line: usize, cursor: Option<(usize, usize)>) -> Task<Message> {
+        let Some(large_file) = self.large_file.clone() else {
+            return Task::none();
+        };
+
+        self.is_loading = true;
+
+        Task::perform(async move { large_file.read_window(first_line).await }, move |result| {
+            Message::LargeWindowLoaded(result.map_err(|error| Error::IoError(error.kind())), cursor)
+        })
+    }
+
+    /// Moves the page of the hex view by the given amount of rows
+    fn scroll_hex_view(&mut self, rows: i64) -> Task<Message> {
+        let Some(hex_view) = &self.hex_view else {
+            return Task::none();
+        };
+
+        let offset = hex_view.offset() as i64 + rows * hex::BYTES_PER_ROW as i64;
+        self.update(Message::LoadHexPage(offset.max(0) as u64))
+    }
+
+    /// The banner shown above a large file, with buttons to move its window
+    fn large_file_banner(&self, large_file: &LargeFile) -> Element<'_, Message> {
+        let first_line = large_file.first_line();
+        let last_line = first_line + self.content.line_count();
+
+        row![
+            text(format!(
+                "Large file ({:.1} MB): highlighting, word wrap and editing are off. Lines {}-{} of {}",
+                large_file.size() as f64 / 1024.0 / 1024.0,
+                first_line + 1,
+                last_line,
+                large_file.line_count()
+            ))
+            .style(text::danger),
+            horizontal_space(),
+            button("Previous")
+                .style(button::secondary)
+                .on_press_maybe((first_line > 0 && !self.is_loading).then(|| {
+                    Message::LoadLargeWindow(first_line.saturating_sub(large_file::WINDOW_LINES))
+                })),
+            button("Next")
+                .style(button::secondary)
+                .on_press_maybe((last_line < large_file.line_count() && !self.is_loading).then_some(Message::LoadLargeWindow(last_line))),
         ]
         .spacing(10)
-        .padding(10)
+        .align_y(Center)
         .into()
     }
 
-    /// Internal iced theme selection
-    pub fn theme(&self) -> Theme {
-        if self._settings.get_theme().is_dark() {
-            Theme::Dark
+    /// Whether the document can be edited
+    fn is_editable(&self) -> bool {
+        !self.is_read_only && self.large_file.is_none() && self.hex_view.is_none()
+    }
+
+    /// How the document is indented: as set by EditorConfig, as detected in the file, or as configured for its language
+    fn indentation(&self) -> indent::Indentation {
+        let indentation = self
+            .detected_indentation
+            .unwrap_or_else(|| self._settings.indentation(self.language()));
+
+        self.editorconfig.indentation(indentation)
+    }
+
+    /// The bracket next to the cursor and its partner, to be highlighted
+    fn matching_brackets(&self) -> Vec<(usize, usize)> {
+        let (line, index) = self.content.cursor_position();
+
+        brackets::find_match(&self.content, line, index)
+            .map(|(bracket, partner)| vec![bracket, partner])
+            .unwrap_or_default()
+    }
+
+    /// Maps the characters of the auto-closing pairs, and Backspace inside an empty pair, to their messages
+    fn pair_binding(&self, key_press: &text_editor::KeyPress) -> Option<Message> {
+        let modifiers = key_press.modifiers;
+        if modifiers.control() || modifiers.alt() || modifiers.logo() {
+            return None;
+        }
+
+        let pairs = self._settings.auto_close_pairs(self.language());
+        if pairs.is_empty() {
+            return None;
+        }
+
+        if key_press.key == keyboard::Key::Named(keyboard::key::Named::Backspace) {
+            if self.content.selection().is_some() {
+                return None;
+            }
+
+            let (line, index) = self.content.cursor_position();
+            let text = self.content.line(line)?.to_string();
+            let before = text.get(..index)?.chars().next_back()?;
+            let after = text.get(index..)?.chars().next()?;
+
+            return pairs.contains(&(before, after)).then_some(Message::DeletePair);
+        }
+
+        let mut characters = key_press.text.as_deref()?.chars();
+        let character = characters
+            .next()
+            .filter(|character| pairs.iter().any(|(open, close)| open == character || close == character))?;
+
+        characters.next().is_none().then_some(Message::TypePairCharacter(character))
+    }
+
+    /// Runs the save transformations over the text, as an edit of the text editor
+    fn transform_before_save(&mut self) {
+        let language = self.language().to_owned();
+        let trim_trailing_whitespace = self
+            .editorconfig
+            .trim_trailing_whitespace
+            .unwrap_or_else(|| self._settings.trim_trailing_whitespace(&language));
+        // A final line break is only removed if EditorConfig asks for it
+        let final_newline = self
+            .editorconfig
+            .insert_final_newline
+            .or(self._settings.insert_final_newline(&language).then_some(true));
+
+        let steps = transform::pipeline(trim_trailing_whitespace, final_newline);
+        let (line, index) = self.content.cursor_position();
+        let column = self.content.line(line).map_or(0, |text| text[..index.min(text.len())].chars().count());
+        let mut changed = false;
+
+        // From the bottom up, so lines that are split don't move the lines still to come
+        for index in (0..self.content.line_count()).rev() {
+            let transformed = self.content.line(index).and_then(|text| transform::line(&steps, &text));
+
+            if let Some(transformed) = transformed {
+                changed |= self.set_lines(index, index, transformed);
+            }
+        }
+
+        let line_count = self.content.line_count();
+        let empty_lines = (0..line_count)
+            .rev()
+            .take_while(|index| self.content.line(*index).is_some_and(|text| text.is_empty()))
+            .count();
+
+        match transform::final_empty_lines(&steps, line_count, empty_lines) {
+            Some(wanted) if wanted > empty_lines => {
+                let last = line_count - 1;
+                let mut lines = self.lines(last, last);
+                lines.resize(1 + wanted - empty_lines, String::new());
+                changed |= self.set_lines(last, last, lines);
+            }
+            Some(wanted) if wanted < empty_lines => {
+                changed |= self.set_lines(line_count - (empty_lines - wanted), line_count - 1, Vec::new());
+            }
+            _ => {}
+        }
+
+        if changed {
+            log::info!("Ran the save transformations: {:?}", steps);
+            cursor::move_to(&mut self.content, line, column);
+        }
+    }
+
+    /// Replaces lines of the editor, starting at the given one, with other lines. Only the lines that differ are edited.
+    fn replace_lines(&mut self, first: usize, old: &[String], new: &[String]) {
+        let mut prefix = old.iter().zip(new).take_while(|(old, new)| old == new).count();
+        let mut suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+
+        // Lines that are only inserted or removed are edited together with a line next to them
+        if prefix + suffix == old.len() || prefix + suffix == new.len() {
+            if prefix > 0 {
+                prefix -= 1;
+            } else {
+                suffix = suffix.saturating_sub(1);
+            }
+        }
+
+        cursor::select_lines(&mut self.content, first + prefix, first + old.len() - suffix - 1);
+        let replacement = new[prefix..new.len() - suffix].join("\n");
+        self.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(replacement))));
+    }
+
+    /// The text of the given lines
+    fn lines(&self, first: usize, last: usize) -> Vec<String> {
+        (first..=last)
+            .filter_map(|line| self.content.line(line).map(|line| line.to_string()))
+            .collect()
+    }
+
+    /// Replaces the given lines with other lines, which may be fewer or none. Returns whether any line changed.
+    fn set_lines(&mut self, mut first: usize, last: usize, mut new: Vec<String>) -> bool {
+        let mut old = self.lines(first, last);
+
+        if old == new {
+            return false;
+        }
+
+        // Removed lines are edited together with a line next to them, or leave an empty document
+        if new.is_empty() {
+            if let Some(next) = self.content.line(last + 1).map(|line| line.to_string()) {
+                old.push(next.clone());
+                new.push(next);
+            } else if let Some(previous) = first.checked_sub(1).and_then(|line| self.content.line(line)).map(|line| line.to_string()) {
+                first -= 1;
+                old.insert(0, previous.clone());
+                new.insert(0, previous);
+            } else {
+                new.push(String::new());
+            }
+        }
+
+        self.replace_lines(first, &old, &new);
+        true
+    }
+
+    /// Replaces the given lines with the result of a function. Returns whether any line changed.
+    fn map_lines(&mut self, first: usize, last: usize, map: impl Fn(&str) -> String) -> bool {
+        let mapped = self.lines(first, last).iter().map(|line| map(line)).collect();
+        self.set_lines(first, last, mapped)
+    }
+
+    /// Inserts text at the cursor
+    fn paste(&mut self, text: String) {
+        if !text.is_empty() {
+            self.perform(text_editor::Action::Edit(text_editor::Edit::Paste(Arc::new(text))));
+        }
+    }
+
+    /// Performs an action in the editor and copies the lines it changed into the document
+    fn perform(&mut self, action: text_editor::Action) {
+        if action.is_edit() {
+            let edit = Edit::before(&self.content);
+            self.content.perform(action);
+
+            // Text appended while following a file is not undone
+            match self.document.apply(edit, &self.content) {
+                Some(change) if self.is_editable() => self.history.record(change),
+                Some(_) => {}
+                None => self.history = History::default(),
+            }
+        } else {
+            self.content.perform(action);
+            self.history.close();
+        }
+    }
+
+    /// Undoes or redoes the changes of a step, then gives it back to the history
+    fn replay(&mut self, step: history::Step, undo: bool) {
+        if undo {
+            for change in step.iter().rev() {
+                self.replace_lines(change.line, &change.new, &change.old);
+            }
+            self.history.undone(step);
         } else {
-            Theme::Light
+            for change in &step {
+                self.replace_lines(change.line, &change.old, &change.new);
+            }
+            self.history.redone(step);
+        }
+
+        self.is_dirty = true;
+    }
+
+    /// Saves the window state once it has not changed for a moment, so moving the window
+    /// does not write the file on every step
+    fn save_window_state(&mut self) -> Task<Message> {
+        self.window_state_version += 1;
+        let version = self.window_state_version;
+
+        Task::perform(tokio::time::sleep(WINDOW_STATE_SAVE_DELAY), move |_| {
+            Message::SaveWindowState(version)
+        })
+    }
+
+    /// Changes the font size of the editor and writes it to the settings file if the zoom is persisted
+    fn set_font_size(&mut self, font_size: f32) {
+        self.font_size = font_size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+        log::info!("Font size set to: {}", self.font_size);
+
+        if self._settings.persist_zoom {
+            if let Err(error) = self._settings.persist_font_size(self.font_size) {
+                log::error!("Failed to save font size: {}", error);
+            }
+        }
+    }
+
+    /// The language of the document, as picked by the user or detected
+    fn language(&self) -> &str {
+        self.language_override.as_deref().unwrap_or(&self.language)
+    }
+
+    /// The format of the settings, in the character set the file was read in
+    fn default_format(&self) -> Format {
+        Format {
+            charset: self.charset,
+            ..self._settings.format(self.language())
+        }
+    }
+
+    /// The line ending and character set the document is saved with, unless it is saved to a new path
+    fn format(&self) -> Format {
+        let format = self.editorconfig.format(self.default_format());
+
+        Format {
+            line_ending: self.line_ending_override.unwrap_or(format.line_ending),
+            charset: self.charset_override.unwrap_or(format.charset),
         }
     }
 
+    /// The command palette, drawn on top of the editor
+    fn palette_view<'a>(&'a self, palette: &'a Palette) -> Element<'a, Message> {
+        let entries = column(palette.matches().into_iter().enumerate().map(|(index, command)| {
+            let chord = self
+                .keymap
+                .chord(command)
+                .map(ToString::to_string)
+                .unwrap_or_default();
+
+            button(row![text(command.title()), horizontal_space(), text(chord)].spacing(10))
+                .width(Fill)
+                .style(if index == palette.selected {
+                    button::primary
+                } else {
+                    button::text
+                })
+                .on_press(Message::PaletteCommandSelected(command))
+                .into()
+        }));
+
+        let palette = container(
+            column![
+                text_input("Type a command...", &palette.query)
+                    .id(PALETTE_INPUT_ID)
+                    .on_input(Message::PaletteQueryChanged)
+                    .on_submit(Message::PaletteSubmitted),
+                scrollable(entries),
+            ]
+            .spacing(10),
+        )
+        .padding(10)
+        .max_width(500)
+        .style(container::bordered_box);
+
+        // Clicking next to the palette closes it
+        opaque(
+            mouse_area(
+                container(opaque(palette))
+                    .center_x(Fill)
+                    .height(Fill)
+                    .padding(40),
+            )
+            .on_press(Message::PaletteClosed),
+        )
+    }
+
+    /// Internal iced theme selection
+    pub fn theme(&self, _window: window::Id) -> Theme {
+        self.app_theme.clone()
+    }
+
     /// Internal iced subscription cycle
     pub fn subscription(&self) -> iced::Subscription<Message> {
         // Subscribe to hotkey events
-        Subscription::run(hotkey_worker)
+        let mut subscriptions = vec![
+            Subscription::run(hotkey_worker),
+            event::listen_with(window_event),
+            event::listen_with(escape_key_press),
+        ];
+
+        // Hidden windows don't need the color scheme, so nothing wakes SBT up while it waits for the hotkey
+        if self._settings.follows_color_scheme() && self.is_visible {
+            subscriptions.push(Subscription::run(appearance::watch).map(Message::ColorSchemeChanged));
+        }
+
+        if self._settings.hide_on_blur {
+            subscriptions.push(event::listen_with(hide_event));
+        }
+
+        if let Some(path) = self.file.clone().filter(|_| self.is_following) {
+            subscriptions.push(Subscription::run_with_id(path.clone(), follow::watch(path)).map(Message::FileChanged));
+        }
+
+        if self.hex_view.is_some() {
+            subscriptions.push(keyboard::on_key_press(|key, modifiers| Some(Message::HexKeyPressed(key, modifiers))));
+        }
+
+        if self.palette.is_some() {
+            subscriptions.push(event::listen_with(palette_key_press));
+        }
+
+        if self.dropdown.as_ref().is_some_and(Dropdown::is_sliding) {
+            subscriptions.push(iced::time::every(dropdown::FRAME_INTERVAL).map(Message::DropdownFrame));
+        }
+
+        Subscription::batch(subscriptions)
     }
 }
 /// Opens a file dialog to select a file to open
-async fn open_file() -> Result<(PathBuf, Arc<String>), Error> {
+async fn open_file(large_file_threshold: u64) -> Result<(PathBuf, Opened, bool), Error> {
     let picked_file = rfd::AsyncFileDialog::new()
         .set_title("Open a text file...")
         .pick_file()
         .await
         .ok_or(Error::DialogClosed)?;
 
-    load_file(picked_file).await
+    load_file(picked_file, large_file_threshold).await
+}
+
+/// Reads the font files of the given directory
+fn load_fonts(directory: &Path) -> Vec<Vec<u8>> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| ["ttf", "otf"].contains(&extension.to_lowercase().as_str()))
+        })
+        .filter_map(|path| {
+            log::info!("Loading font: {:?}", path);
+            std::fs::read(&path)
+                .map_err(|error| log::error!("Failed to read font {:?}: {}", path, error))
+                .ok()
+        })
+        .collect()
 }
 
-/// Asynchronously loads a file from the file system
+/// Asynchronously loads a file from the file system. Files above the threshold, in megabytes, are only indexed.
+/// Also checks whether the file can be written.
 async fn load_file(
     path: impl Into<PathBuf>,
-) -> Result<(PathBuf, Arc<String>), Error> {
+    large_file_threshold: u64,
+) -> Result<(PathBuf, Opened, bool), Error> {
     let path = path.into();
 
-    let contents = tokio::fs::read_to_string(&path)
+    let size = tokio::fs::metadata(&path)
         .await
-        .map(Arc::new)
-        .map_err(|error| Error::IoError(error.kind()))?;
+        .map_err(|error| Error::IoError(error.kind()))?
+        .len();
+
+    let editorconfig = editorconfig::resolve(path.clone()).await;
+    // UTF-16 text is full of NUL bytes, which would make it look binary
+    let is_utf16 = matches!(editorconfig.charset, Some(Charset::Utf16Le | Charset::Utf16Be));
+    let is_single_byte = editorconfig.charset == Some(Charset::Latin1);
+    let is_binary = !is_utf16
+        && hex::is_binary_file(path.clone(), is_single_byte)
+            .await
+            .map_err(|error| Error::IoError(error.kind()))?;
+
+    let opened = if is_binary {
+        HexView::open(path.clone()).await.map(Opened::Binary)
+    } else if size > large_file_threshold * 1024 * 1024 {
+        LargeFile::index(path.clone()).await.map(Opened::Large)
+    } else {
+        Document::load(path.clone(), editorconfig.charset)
+            .await
+            .map(|(document, charset)| Opened::Document(document, charset, editorconfig))
+    }
+    .map_err(|error| Error::IoError(error.kind()))?;
+
+    let is_writable = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || is_writable(&path)).await.unwrap_or(false)
+    };
+
+    Ok((path, opened, is_writable))
+}
+
+/// Whether the file may be written. It is not opened for that, file watchers would take it for a change.
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+
+    // SAFETY: the path is a NUL-terminated string that outlives the call
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
 
-    Ok((path, contents))
+/// Whether the file may be written. Only the read-only attribute is checked, not the access control list.
+#[cfg(not(unix))]
+fn is_writable(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
 }
 
-/// Asynchronously saves a file to the file system
+/// Asynchronously saves a file to the file system. The line ending and character set the user picked
+/// override the format and the EditorConfig properties.
 async fn save_file(
     path: Option<PathBuf>,
-    contents: String,
-) -> Result<PathBuf, Error> {
+    document: Document,
+    format: Format,
+    (line_ending, charset): (Option<LineEnding>, Option<Charset>),
+) -> Result<(PathBuf, editorconfig::Properties), Error> {
     let path = if let Some(path) = path {
         path
     } else {
@@ -344,11 +2100,20 @@ async fn save_file(
             .ok_or(Error::DialogClosed)?
     };
 
-    tokio::fs::write(&path, contents)
+    // The file may be saved to a new place, where other properties apply
+    let editorconfig = editorconfig::resolve(path.clone()).await;
+
+    let format = editorconfig.format(format);
+    let format = Format {
+        line_ending: line_ending.unwrap_or(format.line_ending),
+        charset: charset.unwrap_or(format.charset),
+    };
+
+    document.save(path.clone(), format)
         .await
         .map_err(|error| Error::IoError(error.kind()))?;
 
-    Ok(path)
+    Ok((path, editorconfig))
 }
 
 /// Creates an action button
@@ -394,20 +2159,137 @@ fn icon<'a, Message>(codepoint: char) -> Element<'a, Message> {
     text(codepoint).font(ICON_FONT).into()
 }
 
-/// Worker that listens for global hotkey events
+/// Maps Tab and Shift+Tab to indenting and outdenting
+fn tab_binding(key_press: &text_editor::KeyPress) -> Option<Message> {
+    let modifiers = key_press.modifiers;
+    if key_press.key != keyboard::Key::Named(keyboard::key::Named::Tab) || modifiers.control() || modifiers.alt() || modifiers.logo() {
+        return None;
+    }
+
+    Some(if modifiers.shift() { Message::Outdent } else { Message::Indent })
+}
+
+/// Maps Enter and closing brackets to their indenting messages
+fn indent_binding(key_press: &text_editor::KeyPress) -> Option<Message> {
+    let modifiers = key_press.modifiers;
+    if modifiers.control() || modifiers.alt() || modifiers.logo() {
+        return None;
+    }
+
+    if key_press.key == keyboard::Key::Named(keyboard::key::Named::Enter) && !modifiers.shift() {
+        return Some(Message::NewLine);
+    }
+
+    let mut characters = key_press.text.as_deref()?.chars();
+    let character = characters.next().filter(|character| indent::CLOSERS.contains(character))?;
+
+    characters.next().is_none().then_some(Message::CloseBracket(character))
+}
+
+/// Logs the resident memory of the process, to compare the background modes
+#[cfg(target_os = "linux")]
+fn log_memory_usage(moment: &str) {
+    // The second field of statm is the resident set size, in pages of usually 4 KiB
+    let resident = std::fs::read_to_string("/proc/self/statm")
+        .ok()
+        .and_then(|statm| statm.split_whitespace().nth(1)?.parse::<u64>().ok());
+
+    if let Some(pages) = resident {
+        log::info!("Resident memory {}: {} MB", moment, pages * 4096 / 1024 / 1024);
+    }
+}
+
+/// Reading the resident memory is only implemented on Linux
+#[cfg(not(target_os = "linux"))]
+fn log_memory_usage(_moment: &str) {}
+
+/// Worker that listens for global hotkey events. A dedicated thread blocks on the receiver of
+/// the hotkey manager and forwards the events, so nothing runs while no hotkey is pressed.
 fn hotkey_worker() -> impl Stream<Item = Message> {
     stream::channel(100, |mut sender| async move {
-        // Create channel
-        let receiver = GlobalHotKeyEvent::receiver();
-        // poll for global hotkey events every 50ms
-        loop {
-            if let Ok(event) = receiver.try_recv() {
-                sender
-                    .send(Message::HotkeyPressed(event))
-                    .await
-                    .unwrap();
-                }
-            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let (bridge, mut events) = mpsc::unbounded();
+
+        let spawned = std::thread::Builder::new()
+            .name("hotkey-listener".to_owned())
+            .spawn(move || {
+                while let Ok(event) = GlobalHotKeyEvent::receiver().recv() {
+                    // The subscription is gone, stop listening
+                    if bridge.unbounded_send(event).is_err() {
+                        break;
+                    }
+                }
+            });
+
+        if let Err(error) = spawned {
+            log::error!("Failed to start the hotkey listener: {}", error);
+            return;
+        }
+
+        while let Some(event) = events.next().await {
+            if sender.send(Message::HotkeyPressed(event)).await.is_err() {
+                break;
             }
+        }
     })
-}
\ No newline at end of file
+}
+
+/// Maps the window events that change the window state to their messages
+fn window_event(event: Event, _status: event::Status, _window: window::Id) -> Option<Message> {
+    match event {
+        Event::Window(window::Event::Moved(position)) => Some(Message::WindowMoved(position)),
+        Event::Window(window::Event::Resized(size)) => Some(Message::WindowResized(size)),
+        Event::Window(window::Event::CloseRequested) => Some(Message::WindowCloseRequested),
+        Event::Window(window::Event::Closed) => Some(Message::WindowClosed),
+        _ => None,
+    }
+}
+
+/// Maps the window losing the focus to its message
+fn hide_event(event: Event, _status: event::Status, _window: window::Id) -> Option<Message> {
+    match event {
+        Event::Window(window::Event::Unfocused) => Some(Message::WindowUnfocused),
+        _ => None,
+    }
+}
+
+/// Maps Escape to a single message, which closes the prompt, the command palette or the window in that order
+fn escape_key_press(event: Event, _status: event::Status, _window: window::Id) -> Option<Message> {
+    match event {
+        Event::Keyboard(keyboard::Event::KeyPressed {
+            key: keyboard::Key::Named(keyboard::key::Named::Escape),
+            ..
+        }) => Some(Message::EscapePressed),
+        _ => None,
+    }
+}
+
+/// Maps the keys that navigate the command palette to their messages
+fn palette_key_press(event: Event, _status: event::Status, _window: window::Id) -> Option<Message> {
+    let Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) = event else {
+        return None;
+    };
+
+    match key.as_ref() {
+        keyboard::Key::Named(keyboard::key::Named::ArrowUp) => Some(Message::PaletteSelectionMoved(-1)),
+        keyboard::Key::Named(keyboard::key::Named::ArrowDown) => Some(Message::PaletteSelectionMoved(1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writable_files_are_told_apart_without_touching_them() {
+        let path = std::env::temp_dir().join(format!("sbt-writable-{}.txt", std::process::id()));
+        std::fs::write(&path, "text").unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        assert!(is_writable(&path));
+        assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), modified);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(!is_writable(&path));
+    }
+}
@@ -0,0 +1,442 @@
+//! Support for [EditorConfig](https://editorconfig.org) files. The `.editorconfig` files from the directory of a file
+//! up to the root of the file system, or to a file with `root = true`, are read when the file is opened or saved.
+//! Sections of files closer to the file, and later sections of the same file, take precedence.
+
+use crate::document::{Charset, Format, LineEnding};
+use crate::indent::Indentation;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the files holding the properties
+const FILE_NAME: &str = ".editorconfig";
+
+/// The properties that apply to a file. Properties that are not set leave the settings of the editor alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties {
+    /// Whether the file is indented with tabs (`false`) or spaces
+    pub insert_spaces: Option<bool>,
+    /// The width of a level of indentation, `None` if it is the tab width
+    pub indent_size: Option<usize>,
+    /// The width of a tab
+    pub tab_width: Option<usize>,
+    /// The line ending the file is written with
+    pub end_of_line: Option<LineEnding>,
+    /// The character set the file is read and written in
+    pub charset: Option<Charset>,
+    /// Whether whitespace at the end of lines is removed on save
+    pub trim_trailing_whitespace: Option<bool>,
+    /// Whether the file ends with a line break (`true`) or without one
+    pub insert_final_newline: Option<bool>,
+}
+
+impl Properties {
+    /// Sets a property from a `key = value` pair. Unknown keys and values are ignored.
+    fn set(&mut self, key: &str, value: &str) {
+        let unset = value == "unset";
+
+        match key {
+            "indent_style" => update(&mut self.insert_spaces, unset, match value {
+                "space" => Some(true),
+                "tab" => Some(false),
+                _ => None,
+            }),
+            // `tab` means the tab width is used
+            "indent_size" => update(&mut self.indent_size, unset || value == "tab", value.parse().ok()),
+            "tab_width" => update(&mut self.tab_width, unset, value.parse().ok()),
+            "end_of_line" => update(&mut self.end_of_line, unset, LineEnding::parse(value)),
+            "charset" => update(&mut self.charset, unset, Charset::parse(value)),
+            "trim_trailing_whitespace" => update(&mut self.trim_trailing_whitespace, unset, parse_bool(value)),
+            "insert_final_newline" => update(&mut self.insert_final_newline, unset, parse_bool(value)),
+            _ => {}
+        }
+    }
+
+    /// Applies the indentation properties to the indentation used otherwise
+    pub fn indentation(&self, indentation: Indentation) -> Indentation {
+        let insert_spaces = self.insert_spaces.unwrap_or(indentation.insert_spaces);
+        let width = if insert_spaces {
+            self.indent_size.or(self.tab_width)
+        } else {
+            self.tab_width.or(self.indent_size)
+        };
+
+        Indentation {
+            tab_width: width.unwrap_or(indentation.tab_width).max(1),
+            insert_spaces,
+        }
+    }
+
+    /// Applies the properties to the format the file is written in otherwise
+    pub fn format(&self, format: Format) -> Format {
+        Format {
+            line_ending: self.end_of_line.unwrap_or(format.line_ending),
+            charset: self.charset.unwrap_or(format.charset),
+        }
+    }
+}
+
+/// Finds the properties that apply to a file
+pub async fn resolve(path: PathBuf) -> Properties {
+    tokio::task::spawn_blocking(move || resolve_blocking(&path))
+        .await
+        .unwrap_or_default()
+}
+
+/// Reads the `.editorconfig` files above a file and collects the properties of the sections matching it
+fn resolve_blocking(path: &Path) -> Properties {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut files = Vec::new();
+
+    for directory in Path::new(&path).ancestors().skip(1) {
+        let file = directory.join(FILE_NAME);
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+
+        log::info!("Reading {:?}", file);
+        let is_root = parse(&text).0;
+        files.push((directory.to_string_lossy().into_owned(), text));
+
+        if is_root {
+            break;
+        }
+    }
+
+    let mut properties = Properties::default();
+
+    // Files closer to the file are applied last, so they win
+    for (directory, text) in files.iter().rev() {
+        for (glob, pairs) in parse(text).1 {
+            if section_matches(directory, &glob, &path) {
+                for (key, value) in pairs {
+                    properties.set(&key, &value);
+                }
+            }
+        }
+    }
+
+    properties
+}
+
+/// A section of a file: its glob and its `key = value` pairs, lowercased
+type Section = (String, Vec<(String, String)>);
+
+/// Parses a file into whether it is the root file and its sections
+fn parse(text: &str) -> (bool, Vec<Section>) {
+    let mut is_root = false;
+    let mut sections: Vec<Section> = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(glob) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            sections.push((glob.to_owned(), Vec::new()));
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_lowercase();
+
+        match sections.last_mut() {
+            Some((_, pairs)) => pairs.push((key, value)),
+            // Only the preamble can hold the root property
+            None => is_root |= key == "root" && value == "true",
+        }
+    }
+
+    (is_root, sections)
+}
+
+/// Whether the glob of a section in the `.editorconfig` file of a directory matches a path
+fn section_matches(directory: &str, glob: &str, path: &str) -> bool {
+    let directory = directory.trim_end_matches('/');
+
+    // A glob without a slash matches the file name in any directory below
+    let (glob, path) = if glob.contains('/') {
+        (glob.trim_start_matches('/'), path.strip_prefix(directory).unwrap_or(path).trim_start_matches('/'))
+    } else {
+        (glob, path.rsplit('/').next().unwrap_or(path))
+    };
+
+    let glob: Vec<char> = glob.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+
+    glob_matches(&parse_glob(&glob), &path)
+}
+
+/// A piece of a glob
+#[derive(Debug, Clone)]
+enum Token {
+    /// A character that matches itself
+    Char(char),
+    /// `?`, any character but a slash
+    Any,
+    /// `*`, any characters but slashes
+    Star,
+    /// `**`, any characters
+    DoubleStar,
+    /// `[abc]` or `[!a-z]`, a character out of a set
+    Class(bool, Vec<(char, char)>),
+    /// `{a,b,c}`, one of several globs
+    Alternatives(Vec<Vec<Token>>),
+    /// `{1..3}`, a number in a range
+    Range(i64, i64),
+}
+
+/// Parses a glob into tokens
+fn parse_glob(glob: &[char]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < glob.len() {
+        match glob[index] {
+            '\\' if index + 1 < glob.len() => {
+                tokens.push(Token::Char(glob[index + 1]));
+                index += 1;
+            }
+            '?' => tokens.push(Token::Any),
+            '*' if glob.get(index + 1) == Some(&'*') => {
+                tokens.push(Token::DoubleStar);
+                index += 1;
+            }
+            '*' => tokens.push(Token::Star),
+            '[' => match parse_class(&glob[index + 1..]) {
+                Some((token, length)) => {
+                    tokens.push(token);
+                    index += length;
+                }
+                None => tokens.push(Token::Char('[')),
+            },
+            '{' => match parse_braces(&glob[index + 1..]) {
+                Some((token, length)) => {
+                    tokens.push(token);
+                    index += length;
+                }
+                None => tokens.push(Token::Char('{')),
+            },
+            character => tokens.push(Token::Char(character)),
+        }
+
+        index += 1;
+    }
+
+    tokens
+}
+
+/// Parses the rest of a `[...]` class. Returns the token and the amount of characters up to the closing bracket.
+fn parse_class(glob: &[char]) -> Option<(Token, usize)> {
+    let end = glob.iter().position(|character| *character == ']')?;
+    let (negated, members) = match glob[..end].split_first() {
+        Some(('!', members)) => (true, members),
+        _ => (false, &glob[..end]),
+    };
+
+    let mut ranges = Vec::new();
+    let mut index = 0;
+
+    while index < members.len() {
+        if members.get(index + 1) == Some(&'-') && index + 2 < members.len() {
+            ranges.push((members[index], members[index + 2]));
+            index += 3;
+        } else {
+            ranges.push((members[index], members[index]));
+            index += 1;
+        }
+    }
+
+    Some((Token::Class(negated, ranges), end + 1))
+}
+
+/// Parses the rest of a `{...}` group. Returns the token and the amount of characters up to the closing brace.
+fn parse_braces(glob: &[char]) -> Option<(Token, usize)> {
+    let mut depth = 0;
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut end = None;
+
+    for (index, character) in glob.iter().enumerate() {
+        match character {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            '}' => {
+                parts.push(&glob[start..index]);
+                end = Some(index);
+                break;
+            }
+            ',' if depth == 0 => {
+                parts.push(&glob[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+
+    let end = end?;
+
+    if let [part] = parts.as_slice() {
+        // A single word in braces is a number range or nothing special
+        let text: String = part.iter().collect();
+        let (from, to) = text.split_once("..")?;
+        return Some((Token::Range(from.parse().ok()?, to.parse().ok()?), end + 1));
+    }
+
+    Some((Token::Alternatives(parts.into_iter().map(parse_glob).collect()), end + 1))
+}
+
+/// Whether the tokens of a glob match a path
+fn glob_matches(tokens: &[Token], path: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return path.is_empty();
+    };
+
+    match token {
+        Token::Char(character) => path.first() == Some(character) && glob_matches(rest, &path[1..]),
+        Token::Any => path.first().is_some_and(|character| *character != '/') && glob_matches(rest, &path[1..]),
+        Token::Star => {
+            let length = path.iter().position(|character| *character == '/').unwrap_or(path.len());
+            (0..=length).any(|skip| glob_matches(rest, &path[skip..]))
+        }
+        Token::DoubleStar => (0..=path.len()).any(|skip| glob_matches(rest, &path[skip..])),
+        Token::Class(negated, ranges) => path.first().is_some_and(|character| {
+            let is_member = ranges.iter().any(|(from, to)| (from..=to).contains(&character));
+            is_member != *negated && *character != '/'
+        }) && glob_matches(rest, &path[1..]),
+        Token::Alternatives(alternatives) => alternatives.iter().any(|alternative| {
+            let tokens: Vec<Token> = alternative.iter().chain(rest).cloned().collect();
+            glob_matches(&tokens, path)
+        }),
+        Token::Range(from, to) => {
+            let sign = usize::from(path.first() == Some(&'-'));
+            let digits = path[sign..].iter().take_while(|character| character.is_ascii_digit()).count();
+
+            (1..=digits).any(|length| {
+                let number: String = path[..sign + length].iter().collect();
+                number.parse::<i64>().is_ok_and(|number| (*from..=*to).contains(&number))
+                    && glob_matches(rest, &path[sign + length..])
+            })
+        }
+    }
+}
+
+/// Sets or unsets a property. Invalid values leave it alone.
+fn update<T>(property: &mut Option<T>, unset: bool, value: Option<T>) {
+    if unset {
+        *property = None;
+    } else if value.is_some() {
+        *property = value;
+    }
+}
+
+/// Parses an EditorConfig boolean
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(glob: &str, path: &str) -> bool {
+        section_matches("/project", glob, path)
+    }
+
+    #[test]
+    fn files_are_parsed_into_sections() {
+        let text = "root = true\n# comment\n; comment\n\n[*]\nIndent_Style = Space\n[*.md]\ntrim_trailing_whitespace=false\n";
+        let (is_root, sections) = parse(text);
+
+        assert!(is_root);
+        assert_eq!(sections, vec![
+            ("*".to_owned(), vec![("indent_style".to_owned(), "space".to_owned())]),
+            ("*.md".to_owned(), vec![("trim_trailing_whitespace".to_owned(), "false".to_owned())]),
+        ]);
+    }
+
+    #[test]
+    fn root_only_counts_in_the_preamble() {
+        assert!(!parse("[*]\nroot = true\n").0);
+    }
+
+    #[test]
+    fn globs_without_a_slash_match_file_names_anywhere() {
+        assert!(matches("*.rs", "/project/src/main.rs"));
+        assert!(matches("Makefile", "/project/sub/Makefile"));
+        assert!(!matches("*.rs", "/project/src/main.rs.bak"));
+    }
+
+    #[test]
+    fn globs_with_a_slash_match_from_the_directory() {
+        assert!(matches("src/*.rs", "/project/src/main.rs"));
+        assert!(matches("/src/*.rs", "/project/src/main.rs"));
+        assert!(!matches("src/*.rs", "/project/src/editor/mod.rs"));
+        assert!(matches("src/**.rs", "/project/src/editor/mod.rs"));
+        assert!(matches("**/mod.rs", "/project/src/editor/mod.rs"));
+    }
+
+    #[test]
+    fn classes_and_any_characters_match_one_character() {
+        assert!(matches("file?.txt", "/project/file1.txt"));
+        assert!(!matches("file?.txt", "/project/file12.txt"));
+        assert!(matches("[abc].txt", "/project/b.txt"));
+        assert!(matches("[a-c].txt", "/project/b.txt"));
+        assert!(!matches("[!a-c].txt", "/project/b.txt"));
+        assert!(matches("[!a-c].txt", "/project/d.txt"));
+    }
+
+    #[test]
+    fn braces_match_alternatives_and_number_ranges() {
+        assert!(matches("*.{js,ts}", "/project/index.ts"));
+        assert!(!matches("*.{js,ts}", "/project/index.rs"));
+        assert!(matches("{package.json,*.{yml,yaml}}", "/project/ci.yaml"));
+        assert!(matches("log{1..3}.txt", "/project/log2.txt"));
+        assert!(!matches("log{1..3}.txt", "/project/log4.txt"));
+        assert!(matches("{-3..3}", "/project/-1"));
+        assert!(matches("{single}", "/project/{single}"));
+    }
+
+    #[test]
+    fn escaped_characters_match_themselves() {
+        assert!(matches("\\*.txt", "/project/*.txt"));
+        assert!(!matches("\\*.txt", "/project/a.txt"));
+    }
+
+    #[test]
+    fn later_values_win_and_unset_clears() {
+        let mut properties = Properties::default();
+        properties.set("indent_size", "2");
+        properties.set("indent_size", "four");
+        assert_eq!(properties.indent_size, Some(2));
+
+        properties.set("indent_size", "unset");
+        assert_eq!(properties.indent_size, None);
+
+        properties.set("end_of_line", "crlf");
+        properties.set("charset", "latin1");
+        assert_eq!(properties.format(Format::default()), Format { line_ending: LineEnding::CrLf, charset: Charset::Latin1 });
+    }
+
+    #[test]
+    fn files_closer_to_the_file_win() {
+        let root = std::env::temp_dir().join(format!("sbt-editorconfig-{}", std::process::id()));
+        let directory = root.join("sub");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(root.join(FILE_NAME), "root = true\n[*]\nindent_style = tab\ntab_width = 8\n").unwrap();
+        fs::write(directory.join(FILE_NAME), "[*.rs]\nindent_style = space\n").unwrap();
+
+        let properties = resolve_blocking(&directory.join("main.rs"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(properties.insert_spaces, Some(true));
+        assert_eq!(properties.tab_width, Some(8));
+    }
+}
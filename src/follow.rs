@@ -0,0 +1,175 @@
+//! Following a file that grows, like `tail -f`. The file is checked for appended bytes a few times a second.
+//! When it shrinks or is replaced, as happens when logs are rotated, it is read again from the start.
+
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, Stream};
+use iced::stream;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::document::{Charset, Document};
+
+/// How often the file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A change of the followed file
+#[derive(Debug, Clone)]
+pub enum Change {
+    /// The file was read from the start, when following starts or after it was truncated or replaced
+    Reloaded(Document),
+    /// Text was appended to the file
+    Appended(String),
+}
+
+/// What is known about the followed file
+#[derive(Debug, Default)]
+struct State {
+    /// The amount of bytes read so far, or `None` if the file has to be read from the start
+    offset: Option<u64>,
+    /// The identity of the file, to notice it being replaced
+    identity: Option<u64>,
+    /// The bytes at the end of the last read that are not a complete character or line ending yet
+    pending: Vec<u8>,
+    /// The character set the file was read in, Latin-1 if it is not valid UTF-8
+    charset: Charset,
+}
+
+/// Follows the file at the given path. Sends the whole file first, then every change.
+pub fn watch(path: PathBuf) -> impl Stream<Item = Change> {
+    stream::channel(10, |mut sender: mpsc::Sender<Change>| async move {
+        let mut state = State::default();
+
+        loop {
+            let path = path.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let change = poll(&path, &mut state);
+                (state, change)
+            })
+            .await;
+
+            match result {
+                Ok((next, change)) => {
+                    state = next;
+
+                    match change {
+                        Ok(Some(change)) => {
+                            if sender.send(change).await.is_err() {
+                                return;
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(error) => log::warn!("Failed to follow file: {}", error),
+                    }
+                }
+                Err(error) => {
+                    log::error!("Failed to follow file: {}", error);
+                    return;
+                }
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    })
+}
+
+/// Checks the file once for changes
+fn poll(path: &PathBuf, state: &mut State) -> io::Result<Option<Change>> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let length = metadata.len();
+    let identity = identity(&metadata);
+
+    let replaced = state.identity.is_some() && identity != state.identity;
+    state.identity = identity;
+
+    match state.offset {
+        Some(offset) if !replaced && length >= offset => {
+            if length == offset {
+                return Ok(None);
+            }
+
+            file.seek(SeekFrom::Start(offset))?;
+            file.take(length - offset).read_to_end(&mut state.pending)?;
+            state.offset = Some(length);
+
+            let text = take_complete(&mut state.pending, state.charset);
+            Ok((!text.is_empty()).then_some(Change::Appended(text)))
+        }
+        _ => {
+            if state.offset.is_some() {
+                log::info!("The followed file was truncated or replaced, reading it again");
+            }
+
+            // Reading stops at the length, so text appended meanwhile is read on the next poll
+            let mut bytes = Vec::new();
+            file.take(length).read_to_end(&mut bytes)?;
+
+            let (document, charset) = Document::read(io::Cursor::new(bytes))?;
+            state.charset = charset;
+            state.offset = Some(length);
+            state.pending.clear();
+            Ok(Some(Change::Reloaded(document)))
+        }
+    }
+}
+
+/// Takes the complete characters off the pending bytes, with line endings normalized to `\n`.
+/// Invalid UTF-8 is replaced, a character or `\r\n` that is cut off stays pending.
+/// Files read as Latin-1 are decoded in it.
+fn take_complete(pending: &mut Vec<u8>, charset: Charset) -> String {
+    let mut end = match std::str::from_utf8(pending) {
+        // Every byte is a whole character in Latin-1
+        _ if charset == Charset::Latin1 => pending.len(),
+        Ok(_) => pending.len(),
+        // Only a cut off character at the very end is kept for the next read
+        Err(error) if error.error_len().is_none() => error.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+
+    if end > 0 && pending[end - 1] == b'\r' {
+        end -= 1;
+    }
+
+    let rest = pending.split_off(end);
+    let text = charset.decode(pending).replace("\r\n", "\n");
+    *pending = rest;
+    text
+}
+
+/// The inode of the file, which changes when the file is replaced
+#[cfg(unix)]
+fn identity(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+/// Other platforms only notice a replaced file by it becoming shorter
+#[cfg(not(unix))]
+fn identity(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cut_off_characters_and_line_endings_stay_pending() {
+        let mut pending = b"a\r\nb\xc3".to_vec();
+        assert_eq!(take_complete(&mut pending, Charset::Utf8), "a\nb");
+        assert_eq!(pending, b"\xc3");
+
+        pending.extend_from_slice(b"\xa9\r");
+        assert_eq!(take_complete(&mut pending, Charset::Utf8), "é");
+        assert_eq!(pending, b"\r");
+    }
+
+    #[test]
+    fn latin1_bytes_are_whole_characters() {
+        let mut pending = b"caf\xe9\xc3".to_vec();
+        assert_eq!(take_complete(&mut pending, Charset::Latin1), "caféÃ");
+        assert!(pending.is_empty());
+    }
+}
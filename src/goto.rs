@@ -0,0 +1,74 @@
+//! Parsing of the "go to line" input. The input accepts an absolute `line`, a `line:column` pair
+//! or a relative `+N` / `-N` offset from the current line. Lines and columns are one-based for the user.
+
+/// The position the cursor should jump to. Both values are zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    /// The line to jump to
+    pub line: usize,
+    /// The column to jump to, if one was given
+    pub column: Option<usize>,
+}
+
+/// Parses the user input into a [`Target`]. Returns `None` if the input is not valid.
+pub fn parse(input: &str, current_line: usize) -> Option<Target> {
+    let input = input.trim();
+    let (line, column) = match input.split_once(':') {
+        Some((line, column)) => (line.trim(), Some(column.trim())),
+        None => (input, None),
+    };
+
+    let line = if let Some(offset) = line.strip_prefix('+') {
+        current_line.saturating_add(offset.parse().ok()?)
+    } else if let Some(offset) = line.strip_prefix('-') {
+        current_line.saturating_sub(offset.parse().ok()?)
+    } else {
+        line.parse::<usize>().ok()?.saturating_sub(1)
+    };
+
+    let column = match column {
+        Some(column) => Some(column.parse::<usize>().ok()?.saturating_sub(1)),
+        None => None,
+    };
+
+    Some(Target { line, column })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(line: usize, column: Option<usize>) -> Option<Target> {
+        Some(Target { line, column })
+    }
+
+    #[test]
+    fn parses_absolute_lines() {
+        assert_eq!(parse("12", 0), target(11, None));
+        assert_eq!(parse(" 3 ", 5), target(2, None));
+        // Line zero is clamped to the first line
+        assert_eq!(parse("0", 5), target(0, None));
+    }
+
+    #[test]
+    fn parses_lines_and_columns() {
+        assert_eq!(parse("4:7", 0), target(3, Some(6)));
+        assert_eq!(parse("4 : 1", 0), target(3, Some(0)));
+        assert_eq!(parse("+2:3", 10), target(12, Some(2)));
+    }
+
+    #[test]
+    fn parses_relative_offsets() {
+        assert_eq!(parse("+5", 10), target(15, None));
+        assert_eq!(parse("-5", 10), target(5, None));
+        assert_eq!(parse("-50", 10), target(0, None));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(parse("", 0), None);
+        assert_eq!(parse("abc", 0), None);
+        assert_eq!(parse("3:x", 0), None);
+        assert_eq!(parse("+", 0), None);
+    }
+}
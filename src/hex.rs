@@ -0,0 +1,215 @@
+//! Binary files are shown in a read-only hex view instead of the text editor. Only the page of bytes
+//! that is visible is read from the file, so binary files of any size can be opened.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The amount of bytes shown in a row
+pub const BYTES_PER_ROW: u64 = 16;
+/// The amount of rows shown at once
+pub const ROWS_PER_PAGE: u64 = 32;
+/// The amount of bytes at the start of a file that are checked for binary content
+const SAMPLE_SIZE: usize = 8 * 1024;
+/// The share of bytes in invalid UTF-8 sequences above which a file counts as binary
+const INVALID_RATIO: f32 = 0.1;
+
+/// Checks the start of a file for binary content. Files in a single-byte charset are not expected to be valid UTF-8.
+pub async fn is_binary_file(path: PathBuf, is_single_byte: bool) -> io::Result<bool> {
+    tokio::task::spawn_blocking(move || {
+        let mut sample = Vec::with_capacity(SAMPLE_SIZE);
+        File::open(path)?.take(SAMPLE_SIZE as u64).read_to_end(&mut sample)?;
+
+        Ok(is_binary(&sample, is_single_byte))
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Checks a sample for binary content: NUL bytes or, unless it is in a single-byte charset, a lot of invalid UTF-8
+fn is_binary(sample: &[u8], is_single_byte: bool) -> bool {
+    if sample.contains(&0) {
+        return true;
+    }
+
+    // Every byte is a character in a single-byte charset like Latin-1
+    if is_single_byte {
+        return false;
+    }
+
+    let mut invalid = 0;
+    let mut rest = sample;
+
+    while let Err(error) = std::str::from_utf8(rest) {
+        // A character cut off at the end of the sample is fine
+        let Some(length) = error.error_len() else {
+            break;
+        };
+
+        invalid += length;
+        rest = &rest[error.valid_up_to() + length..];
+    }
+
+    !sample.is_empty() && invalid as f32 / sample.len() as f32 > INVALID_RATIO
+}
+
+/// Parses an offset like `1024`, `0x400` or `400h`
+pub fn parse_offset(input: &str) -> Option<u64> {
+    let input = input.trim();
+
+    if let Some(hex) = input.strip_prefix("0x").or_else(|| input.strip_prefix("0X")).or_else(|| input.strip_suffix(['h', 'H'])) {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        input.parse().ok()
+    }
+}
+
+/// The hex view of a binary file
+#[derive(Debug, Clone)]
+pub struct HexView {
+    /// The path of the file
+    path: PathBuf,
+    /// The size of the file in bytes
+    size: u64,
+    /// The offset of the first byte of the page
+    offset: u64,
+    /// The bytes of the page
+    bytes: Arc<Vec<u8>>,
+}
+
+impl HexView {
+    /// Opens a file in the hex view, reading its first page
+    pub async fn open(path: PathBuf) -> io::Result<Self> {
+        let size = tokio::fs::metadata(&path).await?.len();
+
+        let mut view = HexView {
+            path,
+            size,
+            offset: 0,
+            bytes: Arc::default(),
+        };
+
+        let (offset, bytes) = view.read_page(0).await?;
+        view.set_page(offset, bytes);
+
+        Ok(view)
+    }
+
+    /// Reads the page that shows the given offset. Returns the offset of the page and its bytes.
+    pub async fn read_page(&self, offset: u64) -> io::Result<(u64, Arc<Vec<u8>>)> {
+        let path = self.path.clone();
+        let offset = self.page_offset(offset);
+
+        tokio::task::spawn_blocking(move || {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(offset))?;
+
+            let mut bytes = Vec::new();
+            file.take(BYTES_PER_ROW * ROWS_PER_PAGE).read_to_end(&mut bytes)?;
+
+            Ok((offset, Arc::new(bytes)))
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Sets the page that was read
+    pub fn set_page(&mut self, offset: u64, bytes: Arc<Vec<u8>>) {
+        self.offset = offset;
+        self.bytes = bytes;
+    }
+
+    /// The offset of the first byte of the page
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The offset of the page starting at the row of the given offset, kept inside the file
+    fn page_offset(&self, offset: u64) -> u64 {
+        let last_row = self.size.saturating_sub(1) / BYTES_PER_ROW;
+        let last_page = last_row.saturating_sub(ROWS_PER_PAGE - 1) * BYTES_PER_ROW;
+
+        (offset / BYTES_PER_ROW * BYTES_PER_ROW).min(last_page)
+    }
+
+    /// The rows of the page as offset, hex bytes and printable characters
+    pub fn rows(&self) -> String {
+        self.bytes
+            .chunks(BYTES_PER_ROW as usize)
+            .enumerate()
+            .map(|(index, row)| {
+                let hex: Vec<String> = (0..BYTES_PER_ROW as usize)
+                    .map(|column| row.get(column).map_or("  ".to_owned(), |byte| format!("{byte:02x}")))
+                    .collect();
+                let ascii: String = row
+                    .iter()
+                    .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { char::from(*byte) } else { '.' })
+                    .collect();
+
+                format!(
+                    "{:08x}  {}  {}  |{}|",
+                    self.offset + index as u64 * BYTES_PER_ROW,
+                    hex[..8].join(" "),
+                    hex[8..].join(" "),
+                    ascii
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_not_binary() {
+        assert!(!is_binary(b"", false));
+        assert!(!is_binary("plain text, with ünïcödé\n".as_bytes(), false));
+    }
+
+    #[test]
+    fn a_character_cut_off_at_the_end_is_not_binary() {
+        let sample = "aé".as_bytes();
+        assert!(!is_binary(&sample[..sample.len() - 1], false));
+    }
+
+    #[test]
+    fn nul_bytes_are_binary() {
+        assert!(is_binary(b"PNG\0\0\0", false));
+        assert!(is_binary(b"PNG\0\0\0", true));
+    }
+
+    #[test]
+    fn latin1_is_only_binary_without_its_charset() {
+        let sample = b"caf\xe9 cr\xe8me br\xfbl\xe9e";
+        assert!(is_binary(sample, false));
+        assert!(!is_binary(sample, true));
+    }
+
+    #[test]
+    fn mostly_ascii_latin1_is_text_without_its_charset() {
+        let mut sample = "ASCII text on a line\n".repeat(100).into_bytes();
+        sample.extend_from_slice(b"caf\xe9 cr\xe8me\n");
+        assert!(!is_binary(&sample, false));
+    }
+
+    #[test]
+    fn offsets_are_decimal_or_hex() {
+        assert_eq!(parse_offset("1024"), Some(1024));
+        assert_eq!(parse_offset(" 0x400 "), Some(1024));
+        assert_eq!(parse_offset("0X400"), Some(1024));
+        assert_eq!(parse_offset("400h"), Some(1024));
+        assert_eq!(parse_offset("400H"), Some(1024));
+        assert_eq!(parse_offset("0xg"), None);
+        assert_eq!(parse_offset(""), None);
+        assert_eq!(parse_offset("-1"), None);
+    }
+}
@@ -0,0 +1,474 @@
+//! The syntax highlighter of the editor, built on syntect. Next to the built-in themes and syntaxes it loads
+//! TextMate `.tmTheme` themes from the `themes` directory and `.sublime-syntax` definitions from the `syntaxes`
+//! directory next to the settings file. Syntaxes for a few languages syntect lacks, like Dockerfile and TOML,
+//! are bundled.
+
+use iced::advanced::text::highlighter::{self, Format};
+use iced::font::{self, Font};
+use iced::theme::Palette;
+use iced::Color;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::LazyLock;
+use syntect::highlighting;
+use syntect::parsing;
+
+use crate::settings;
+
+/// The name of the directory with the user themes
+static THEMES_DIR_NAME: &str = "themes";
+/// The name of the directory with the user syntaxes
+static SYNTAXES_DIR_NAME: &str = "syntaxes";
+/// The theme that is used if the configured one does not exist
+static FALLBACK_THEME: &str = "Solarized (dark)";
+
+/// The built-in syntaxes together with the ones of the user
+static SYNTAXES: LazyLock<parsing::SyntaxSet> = LazyLock::new(load_syntaxes);
+/// Syntaxes shipped with SBT for languages syntect has none for. They are only built when one of them is used.
+static BUNDLED_SYNTAXES: LazyLock<parsing::SyntaxSet> = LazyLock::new(load_bundled_syntaxes);
+
+/// The definitions of the bundled syntaxes
+static BUNDLED_SYNTAX_DEFINITIONS: &[&str] = &[
+    include_str!("assets/syntaxes/CMake.sublime-syntax"),
+    include_str!("assets/syntaxes/Dockerfile.sublime-syntax"),
+    include_str!("assets/syntaxes/TOML.sublime-syntax"),
+    include_str!("assets/syntaxes/Vim.sublime-syntax"),
+];
+/// The built-in themes together with the ones of the user
+static THEMES: LazyLock<highlighting::ThemeSet> = LazyLock::new(load_themes);
+
+/// The amount of lines between two parser snapshots
+const LINES_PER_SNAPSHOT: usize = 50;
+
+/// Loads the built-in syntaxes and adds the ones from the syntaxes directory
+fn load_syntaxes() -> parsing::SyntaxSet {
+    let directory = settings::config_dir().join(SYNTAXES_DIR_NAME);
+    let defaults = parsing::SyntaxSet::load_defaults_nonewlines();
+
+    // Building the set again takes a while, so it is only done if there is something to add
+    if !contains_syntaxes(&directory) {
+        return defaults;
+    }
+
+    log::info!("Loading syntaxes from: {:?}", directory);
+    let mut builder = defaults.into_builder();
+
+    if let Err(error) = builder.add_from_folder(&directory, false) {
+        log::error!("Failed to load syntaxes: {}", error);
+    }
+
+    builder.build()
+}
+
+/// Whether a directory or one below it holds `.sublime-syntax` files
+fn contains_syntaxes(directory: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return false;
+    };
+
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        if path.is_dir() {
+            contains_syntaxes(&path)
+        } else {
+            path.extension().is_some_and(|extension| extension == "sublime-syntax")
+        }
+    })
+}
+
+/// Builds the syntaxes shipped with SBT
+fn load_bundled_syntaxes() -> parsing::SyntaxSet {
+    let mut builder = parsing::SyntaxSetBuilder::new();
+
+    for definition in BUNDLED_SYNTAX_DEFINITIONS {
+        match parsing::SyntaxDefinition::load_from_str(definition, false, None) {
+            Ok(syntax) => builder.add(syntax),
+            Err(error) => log::error!("Failed to load a bundled syntax: {}", error),
+        }
+    }
+
+    builder.build()
+}
+
+/// Loads the built-in themes and adds the ones from the themes directory
+fn load_themes() -> highlighting::ThemeSet {
+    let directory = settings::config_dir().join(THEMES_DIR_NAME);
+    let mut themes = highlighting::ThemeSet::load_defaults();
+
+    if directory.is_dir() {
+        log::info!("Loading themes from: {:?}", directory);
+
+        if let Err(error) = themes.add_from_folder(&directory) {
+            log::error!("Failed to load themes: {}", error);
+        }
+    }
+
+    themes
+}
+
+/// A highlighting theme, either a built-in one or one loaded from the themes directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// The key of the theme in the theme set
+    key: String,
+}
+
+impl Theme {
+    /// Finds a theme by name. The built-in themes have short aliases, user themes are named
+    /// after their file name without the extension. Falls back to Solarized if the theme does not exist.
+    pub fn from_name(name: &str) -> Self {
+        let key = match name.to_lowercase().as_str() {
+            "eighties" => "base16-eighties.dark",
+            "mocha" => "base16-mocha.dark",
+            "ocean" => "base16-ocean.dark",
+            "github" | "inspired github" => "InspiredGitHub",
+            "solarized" | "solarized dark" => "Solarized (dark)",
+            "solarized light" => "Solarized (light)",
+            name => THEMES
+                .themes
+                .keys()
+                .find(|key| key.to_lowercase() == name)
+                .map(String::as_str)
+                .unwrap_or_else(|| {
+                    log::warn!("Unknown theme {}, falling back to {}", name, FALLBACK_THEME);
+                    FALLBACK_THEME
+                }),
+        };
+
+        Theme { key: key.to_owned() }
+    }
+
+    /// The names of all the available themes
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        THEMES.themes.keys().map(String::as_str)
+    }
+
+    /// The name of the theme
+    pub fn name(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns `true` if the background of the theme is dark
+    pub fn is_dark(&self) -> bool {
+        self.get()
+            .settings
+            .background
+            .map(|color| {
+                let luminance = 0.2126 * f32::from(color.r) + 0.7152 * f32::from(color.g) + 0.0722 * f32::from(color.b);
+                luminance < 128.0
+            })
+            .unwrap_or(true)
+    }
+
+    /// The colors of the theme, used to style the rest of the user interface.
+    /// Colors the theme does not define are taken from the default dark or light palette.
+    pub fn palette(&self) -> Palette {
+        let base = if self.is_dark() { Palette::DARK } else { Palette::LIGHT };
+        let settings = &self.get().settings;
+
+        Palette {
+            background: settings.background.map(to_color).unwrap_or(base.background),
+            text: settings.foreground.map(to_color).unwrap_or(base.text),
+            primary: settings.accent.or(settings.caret).map(to_color).unwrap_or(base.primary),
+            ..base
+        }
+    }
+
+    /// The syntect theme
+    fn get(&self) -> &'static highlighting::Theme {
+        &THEMES.themes[&self.key]
+    }
+}
+
+/// A syntax highlighter
+#[derive(Debug)]
+pub struct Highlighter {
+    /// The syntax of the highlighted language
+    syntax: &'static parsing::SyntaxReference,
+    /// The set the syntax belongs to
+    syntaxes: &'static parsing::SyntaxSet,
+    /// The highlighter of the theme
+    highlighter: highlighting::Highlighter<'static>,
+    /// The parser snapshots, one every [`LINES_PER_SNAPSHOT`] lines
+    caches: Vec<(parsing::ParseState, parsing::ScopeStack)>,
+    /// The line that is highlighted next
+    current_line: usize,
+    /// The font the bold and italic highlights are based on
+    font: Font,
+    /// The settings the highlighter was created or last updated with
+    settings: Settings,
+    /// The style of the highlighted brackets
+    bracket_style: highlighting::StyleModifier,
+}
+
+/// The settings of a [`Highlighter`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// The theme used for highlighting
+    pub theme: Theme,
+    /// The extension of the file or the name of the language to highlight
+    pub token: String,
+    /// The font of the editor, bold and italic highlights are based on it
+    pub font: Font,
+    /// The brackets to highlight, as line and byte index
+    pub brackets: Vec<(usize, usize)>,
+}
+
+/// Finds the syntax for a token and the set it belongs to. The syntaxes of the user come first,
+/// then the bundled ones, and plain text if none matches.
+fn find_syntax(token: &str) -> (&'static parsing::SyntaxSet, &'static parsing::SyntaxReference) {
+    if let Some(syntax) = SYNTAXES.find_syntax_by_token(token) {
+        return (&SYNTAXES, syntax);
+    }
+
+    match BUNDLED_SYNTAXES.find_syntax_by_token(token) {
+        Some(syntax) => (&BUNDLED_SYNTAXES, syntax),
+        None => (&SYNTAXES, SYNTAXES.find_syntax_plain_text()),
+    }
+}
+
+impl highlighter::Highlighter for Highlighter {
+    type Settings = Settings;
+    type Highlight = Highlight;
+
+    type Iterator<'a> = Box<dyn Iterator<Item = (Range<usize>, Self::Highlight)> + 'a>;
+
+    fn new(settings: &Self::Settings) -> Self {
+        let (syntaxes, syntax) = find_syntax(&settings.token);
+
+        Highlighter {
+            syntax,
+            syntaxes,
+            highlighter: highlighting::Highlighter::new(settings.theme.get()),
+            caches: vec![(parsing::ParseState::new(syntax), parsing::ScopeStack::new())],
+            current_line: 0,
+            font: settings.font,
+            settings: settings.clone(),
+            bracket_style: bracket_style(&settings.theme),
+        }
+    }
+
+    fn update(&mut self, new_settings: &Self::Settings) {
+        let old_settings = std::mem::replace(&mut self.settings, new_settings.clone());
+
+        // When only the brackets moved, the lines before them keep their highlights
+        let only_brackets_moved = old_settings.theme == new_settings.theme
+            && old_settings.token == new_settings.token
+            && old_settings.font == new_settings.font;
+
+        if only_brackets_moved {
+            let first_line = old_settings.brackets.iter().chain(&new_settings.brackets).map(|(line, _)| *line).min();
+
+            if let Some(line) = first_line.filter(|line| *line < self.current_line) {
+                self.change_line(line);
+            }
+            return;
+        }
+
+        (self.syntaxes, self.syntax) = find_syntax(&new_settings.token);
+        self.highlighter = highlighting::Highlighter::new(new_settings.theme.get());
+        self.font = new_settings.font;
+        self.bracket_style = bracket_style(&new_settings.theme);
+
+        // Restart the highlighter
+        self.caches.clear();
+        self.change_line(0);
+    }
+
+    fn change_line(&mut self, line: usize) {
+        let snapshot = line / LINES_PER_SNAPSHOT;
+
+        if snapshot <= self.caches.len() {
+            self.caches.truncate(snapshot);
+            self.current_line = snapshot * LINES_PER_SNAPSHOT;
+        } else {
+            self.caches.truncate(1);
+            self.current_line = 0;
+        }
+
+        let (parser, stack) = self.caches.last().cloned().unwrap_or_else(|| {
+            (parsing::ParseState::new(self.syntax), parsing::ScopeStack::new())
+        });
+
+        self.caches.push((parser, stack));
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        if self.current_line / LINES_PER_SNAPSHOT >= self.caches.len() {
+            let (parser, stack) = self.caches.last().expect("Caches must not be empty");
+
+            self.caches.push((parser.clone(), stack.clone()));
+        }
+
+        let line_number = self.current_line;
+        self.current_line += 1;
+
+        // Highlighted after the syntax, so they win
+        let brackets: Vec<_> = self
+            .settings
+            .brackets
+            .iter()
+            .filter(|(bracket_line, index)| *bracket_line == line_number && *index < line.len())
+            .map(|(_, index)| (*index..*index + 1, Highlight(self.bracket_style, self.font)))
+            .collect();
+
+        let (parser, stack) = self.caches.last_mut().expect("Caches must not be empty");
+        let ops = parser.parse_line(line, self.syntaxes).unwrap_or_default();
+        let highlighter = &self.highlighter;
+        let font = self.font;
+
+        Box::new(
+            ScopeRangeIterator {
+                ops,
+                line_length: line.len(),
+                index: 0,
+                last_str_index: 0,
+            }
+            .filter_map(move |(range, scope)| {
+                let _ = stack.apply(&scope);
+
+                if range.is_empty() {
+                    None
+                } else {
+                    Some((range, Highlight(highlighter.style_mod_for_stack(&stack.scopes), font)))
+                }
+            })
+            .chain(brackets),
+        )
+    }
+
+    fn current_line(&self) -> usize {
+        self.current_line
+    }
+}
+
+/// A highlight produced by the [`Highlighter`]
+#[derive(Debug)]
+pub struct Highlight(highlighting::StyleModifier, Font);
+
+impl Highlight {
+    /// Converts the highlight to the text format used by the editor
+    pub fn to_format(&self) -> Format<Font> {
+        let color = self.0.foreground.map(to_color);
+
+        let font = self.0.font_style.and_then(|style| {
+            let bold = style.contains(highlighting::FontStyle::BOLD);
+            let italic = style.contains(highlighting::FontStyle::ITALIC);
+
+            (bold || italic).then_some(Font {
+                weight: if bold { font::Weight::Bold } else { font::Weight::Normal },
+                style: if italic { font::Style::Italic } else { font::Style::Normal },
+                ..self.1
+            })
+        });
+
+        Format { color, font }
+    }
+}
+
+/// The style of highlighted brackets: bold, in the bracket color of the theme or its caret color
+fn bracket_style(theme: &Theme) -> highlighting::StyleModifier {
+    let settings = &theme.get().settings;
+
+    highlighting::StyleModifier {
+        foreground: settings.brackets_foreground.or(settings.caret).or(settings.accent),
+        background: None,
+        font_style: Some(highlighting::FontStyle::BOLD),
+    }
+}
+
+/// Converts a syntect color to an iced color
+fn to_color(color: highlighting::Color) -> Color {
+    Color::from_rgba8(color.r, color.g, color.b, f32::from(color.a) / 255.0)
+}
+
+/// Splits the parser operations of a line into the ranges they apply to
+struct ScopeRangeIterator {
+    /// The operations and the byte index they start at
+    ops: Vec<(usize, parsing::ScopeStackOp)>,
+    /// The length of the line in bytes
+    line_length: usize,
+    /// The index of the next operation
+    index: usize,
+    /// The byte index the previous range ended at
+    last_str_index: usize,
+}
+
+impl Iterator for ScopeRangeIterator {
+    type Item = (Range<usize>, parsing::ScopeStackOp);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index > self.ops.len() {
+            return None;
+        }
+
+        let next_str_index = if self.index == self.ops.len() {
+            self.line_length
+        } else {
+            self.ops[self.index].0
+        };
+
+        let range = self.last_str_index..next_str_index;
+        self.last_str_index = next_str_index;
+
+        let op = if self.index == 0 {
+            parsing::ScopeStackOp::Noop
+        } else {
+            self.ops[self.index - 1].1.clone()
+        };
+
+        self.index += 1;
+        Some((range, op))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_bundled_syntaxes() {
+        assert_eq!(BUNDLED_SYNTAXES.syntaxes().len(), BUNDLED_SYNTAX_DEFINITIONS.len());
+
+        for (token, name) in [("dockerfile", "Dockerfile"), ("cmake", "CMake"), ("toml", "TOML"), ("vim", "Vim Script")] {
+            assert_eq!(find_syntax(token).1.name, name);
+        }
+
+        assert_eq!(find_syntax("rs").1.name, "Rust");
+        assert_eq!(find_syntax("unknown").1.name, "Plain Text");
+    }
+
+    #[test]
+    fn finds_syntaxes_of_the_user() {
+        let directory = std::env::temp_dir().join(format!("sbt-syntaxes-{}", std::process::id()));
+        fs::create_dir_all(directory.join("nested")).unwrap();
+        assert!(!contains_syntaxes(&directory));
+
+        fs::write(directory.join("nested").join("Custom.sublime-syntax"), "").unwrap();
+        assert!(contains_syntaxes(&directory));
+        assert!(!contains_syntaxes(&directory.join("missing")));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn bundled_syntaxes_parse() {
+        let samples = [
+            ("dockerfile", vec!["# base", "FROM rust:1 AS build", "RUN cargo build --release \"$HOME\""]),
+            ("cmake", vec!["cmake_minimum_required(VERSION 3.20)", "if(WIN32)", "  set(NAME \"${PROJECT}\") # x"]),
+            ("toml", vec!["[package]", "name = \"sbt\" # x", "date = 2024-01-01", "text = '''", "'''"]),
+            ("vim", vec!["\" comment", "set number", "let g:x = \"a\" \" trailing", "nnoremap <leader>w :w<CR>"]),
+        ];
+
+        for (token, lines) in samples {
+            let (syntaxes, syntax) = find_syntax(token);
+            let mut parser = parsing::ParseState::new(syntax);
+
+            for line in lines {
+                let ops = parser.parse_line(line, syntaxes).unwrap();
+                assert!(!ops.is_empty(), "{token}: {line:?} was not highlighted");
+            }
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Undo and redo. Every edit of the text editor is recorded as the lines it replaced and the lines it replaced
+//! them with, as copied into the document. Commands that edit several times, like the line operations or the
+//! save transformations, are recorded as one step, and typing on a line is one step until the cursor moves.
+
+use crate::document::Change;
+use std::collections::VecDeque;
+
+/// The amount of steps that can be undone
+const MAX_STEPS: usize = 1000;
+
+/// The changes of the document that are undone and redone at once
+pub type Step = Vec<Change>;
+
+/// The steps that can be undone and redone
+#[derive(Debug, Default)]
+pub struct History {
+    /// The steps to undo, the last one first
+    undo: VecDeque<Step>,
+    /// The steps to redo, the last one first
+    redo: Vec<Step>,
+    /// The changes of the command that is running, and how deeply commands are nested
+    group: Option<(Step, usize)>,
+    /// Whether typing continues the last step
+    is_typing: bool,
+    /// Whether a step is being undone or redone, whose edits are not recorded again
+    is_replaying: bool,
+}
+
+impl History {
+    /// Records the change of an edit
+    pub fn record(&mut self, change: Change) {
+        if self.is_replaying {
+            return;
+        }
+
+        self.redo.clear();
+
+        if let Some((changes, _)) = &mut self.group {
+            changes.push(change);
+            return;
+        }
+
+        // Typing on the same line replaces the last line of the step again
+        let last = self.undo.back_mut().and_then(|step| step.last_mut());
+        if let Some(last) = last.filter(|last| self.is_typing && continues(last, &change)) {
+            last.new = change.new;
+            return;
+        }
+
+        self.push(vec![change]);
+        self.is_typing = true;
+    }
+
+    /// Ends the typing step, e.g. because the cursor moved
+    pub fn close(&mut self) {
+        self.is_typing = false;
+    }
+
+    /// Starts a command, whose edits are recorded as one step. Commands can be nested.
+    pub fn begin(&mut self) {
+        match &mut self.group {
+            Some((_, depth)) => *depth += 1,
+            None => self.group = Some((Vec::new(), 1)),
+        }
+    }
+
+    /// Ends a command
+    pub fn end(&mut self) {
+        let Some((changes, depth)) = &mut self.group else {
+            return;
+        };
+
+        if *depth > 1 {
+            *depth -= 1;
+            return;
+        }
+
+        let changes = std::mem::take(changes);
+        self.group = None;
+        self.is_typing = false;
+
+        if !changes.is_empty() {
+            self.push(changes);
+        }
+    }
+
+    /// Takes the last step to undo it. Edits are not recorded until it is given back by [`History::undone`].
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.undo.pop_back()?;
+        self.is_replaying = true;
+        Some(step)
+    }
+
+    /// Gives back a step that was undone, so it can be redone
+    pub fn undone(&mut self, step: Step) {
+        self.redo.push(step);
+        self.is_replaying = false;
+        self.is_typing = false;
+    }
+
+    /// Takes the last undone step to redo it. Edits are not recorded until it is given back by [`History::redone`].
+    pub fn redo(&mut self) -> Option<Step> {
+        let step = self.redo.pop()?;
+        self.is_replaying = true;
+        Some(step)
+    }
+
+    /// Gives back a step that was redone, so it can be undone again
+    pub fn redone(&mut self, step: Step) {
+        self.undo.push_back(step);
+        self.is_replaying = false;
+        self.is_typing = false;
+    }
+
+    /// Adds a step to undo, dropping the oldest one if there are too many
+    fn push(&mut self, step: Step) {
+        if self.undo.len() == MAX_STEPS {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(step);
+    }
+}
+
+/// Whether a change continues typing on the line of the last change
+fn continues(last: &Change, change: &Change) -> bool {
+    last.line == change.line && last.new.len() == 1 && change.old == last.new && change.new.len() == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(line: usize, old: &[&str], new: &[&str]) -> Change {
+        Change {
+            line,
+            old: old.iter().map(|line| line.to_string()).collect(),
+            new: new.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn typing_on_a_line_is_one_step() {
+        let mut history = History::default();
+        history.record(change(0, &[""], &["a"]));
+        history.record(change(0, &["a"], &["ab"]));
+
+        let step = history.undo().unwrap();
+        assert_eq!(step, vec![change(0, &[""], &["ab"])]);
+        history.undone(step);
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn moving_the_cursor_ends_a_step() {
+        let mut history = History::default();
+        history.record(change(0, &[""], &["a"]));
+        history.close();
+        history.record(change(0, &["a"], &["ab"]));
+
+        assert_eq!(history.undo().unwrap(), vec![change(0, &["a"], &["ab"])]);
+    }
+
+    #[test]
+    fn line_breaks_start_a_step() {
+        let mut history = History::default();
+        history.record(change(0, &[""], &["a"]));
+        history.record(change(0, &["a"], &["a", ""]));
+
+        assert_eq!(history.undo().unwrap(), vec![change(0, &["a"], &["a", ""])]);
+    }
+
+    #[test]
+    fn commands_are_one_step() {
+        let mut history = History::default();
+        history.begin();
+        history.record(change(0, &["a"], &["b"]));
+        history.begin();
+        history.record(change(2, &["c"], &[]));
+        history.end();
+        history.record(change(3, &["d"], &["e"]));
+        history.end();
+
+        assert_eq!(history.undo().unwrap().len(), 3);
+        history.undone(Vec::new());
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn commands_without_edits_leave_no_step() {
+        let mut history = History::default();
+        history.begin();
+        history.end();
+
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn replayed_edits_are_not_recorded() {
+        let mut history = History::default();
+        history.record(change(0, &["a"], &["b"]));
+
+        let step = history.undo().unwrap();
+        history.record(change(0, &["b"], &["a"]));
+        history.undone(step);
+
+        let step = history.redo().unwrap();
+        assert_eq!(step, vec![change(0, &["a"], &["b"])]);
+        history.redone(step);
+        assert!(history.redo().is_none());
+        assert!(history.undo().is_some());
+    }
+
+    #[test]
+    fn edits_clear_the_steps_to_redo() {
+        let mut history = History::default();
+        history.record(change(0, &["a"], &["b"]));
+        let step = history.undo().unwrap();
+        history.undone(step);
+
+        history.record(change(0, &["a"], &["c"]));
+        assert!(history.redo().is_none());
+    }
+
+    #[test]
+    fn old_steps_are_dropped() {
+        let mut history = History::default();
+        for line in 0..MAX_STEPS + 10 {
+            history.close();
+            history.record(change(line, &["a"], &["b"]));
+        }
+
+        assert_eq!(history.undo.len(), MAX_STEPS);
+        assert_eq!(history.undo.front().unwrap()[0].line, 10);
+    }
+}
@@ -0,0 +1,338 @@
+//! Automatic indentation. A new line starts with the indentation of the line before it, one level deeper
+//! after an opener like `{` or a Python `:`, and Markdown list items are continued.
+//! Typing a closing bracket at the start of a line removes one level again.
+//! Files are indented with tabs or spaces as configured, unless they already use the other style.
+
+/// The amount of lines whose indentation is looked at to detect the style of a file
+pub const DETECT_LINES: usize = 500;
+
+/// The closing brackets that remove a level of indentation
+pub const CLOSERS: &[char] = &[')', ']', '}'];
+
+/// The line endings that increase the indentation of the next line, if the language has none configured
+pub fn default_openers(language: &str) -> &'static [&'static str] {
+    match language {
+        "py" | "python" | "yaml" | "yml" | "nim" => &[":"],
+        "md" | "markdown" | "txt" => &[],
+        _ => &["{", "[", "("],
+    }
+}
+
+/// How a document is indented
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Indentation {
+    /// The width of a level of indentation, and of a tab
+    pub tab_width: usize,
+    /// Whether a level is made of spaces instead of a tab
+    pub insert_spaces: bool,
+}
+
+impl Indentation {
+    /// One level of indentation
+    pub fn unit(&self) -> String {
+        if self.insert_spaces {
+            " ".repeat(self.tab_width)
+        } else {
+            "\t".to_owned()
+        }
+    }
+
+    /// What Tab inserts at the given character column: a tab, or spaces up to the next tab stop
+    pub fn tab_at(&self, column: usize) -> String {
+        if self.insert_spaces {
+            " ".repeat(self.tab_width - column % self.tab_width)
+        } else {
+            "\t".to_owned()
+        }
+    }
+
+    /// The width of a text, with tabs reaching to the next tab stop
+    pub fn width(&self, text: &str) -> usize {
+        text.chars().fold(0, |width, character| match character {
+            '\t' => width + self.tab_width - width % self.tab_width,
+            _ => width + 1,
+        })
+    }
+
+    /// Rewrites the leading whitespace of a line in the style of the indentation
+    pub fn convert(&self, line: &str) -> String {
+        let whitespace = leading_whitespace(line);
+        let width = self.width(whitespace);
+
+        let indent = if self.insert_spaces {
+            " ".repeat(width)
+        } else {
+            format!("{}{}", "\t".repeat(width / self.tab_width), " ".repeat(width % self.tab_width))
+        };
+
+        format!("{indent}{}", &line[whitespace.len()..])
+    }
+
+    /// The amount of characters Shift+Tab removes from the start of a line
+    pub fn outdent(&self, line: &str) -> usize {
+        if line.starts_with('\t') {
+            return 1;
+        }
+
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        spaces.min(self.tab_width)
+    }
+}
+
+/// Detects the indentation of a text, if enough of its lines are indented.
+/// Files indented with tabs keep the configured tab width.
+pub fn detect(text: &str, tab_width: usize) -> Option<Indentation> {
+    let mut tabs = 0;
+    let mut spaces = 0;
+    // How often each step between the indentation of two lines was seen, for steps of 1 to 8 spaces
+    let mut steps = [0; 9];
+    let mut previous = 0;
+
+    for line in text.lines().take(DETECT_LINES).filter(|line| !line.trim().is_empty()) {
+        let whitespace = leading_whitespace(line);
+
+        if whitespace.starts_with('\t') {
+            tabs += 1;
+            continue;
+        }
+
+        let width = whitespace.len() - whitespace.trim_start_matches(' ').len();
+        if width > 0 {
+            spaces += 1;
+        }
+
+        let step = width.abs_diff(previous);
+        if (1..steps.len()).contains(&step) {
+            steps[step] += 1;
+        }
+        previous = width;
+    }
+
+    if tabs == 0 && spaces == 0 {
+        return None;
+    }
+
+    if tabs > spaces {
+        return Some(Indentation {
+            tab_width,
+            insert_spaces: false,
+        });
+    }
+
+    // Prefer the narrower step on a tie, a file indented by 2 also has steps of 4 where two levels end
+    let width = (2..steps.len()).rev().max_by_key(|step| steps[*step]).filter(|step| steps[*step] > 0)?;
+
+    Some(Indentation {
+        tab_width: width,
+        insert_spaces: true,
+    })
+}
+
+/// What to insert after a line break
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewLine {
+    /// The text the new line starts with
+    pub indent: String,
+    /// The indentation of another line for the closing bracket, if the cursor was between a pair of brackets
+    pub closing_indent: Option<String>,
+}
+
+/// Decides how the new line starts, from the text before and after the cursor on the line that is broken
+pub fn new_line(before: &str, after: &str, language: &str, openers: &[String], unit: &str) -> NewLine {
+    let base = leading_whitespace(before);
+
+    if matches!(language, "md" | "markdown") {
+        if let Some(marker) = list_marker(&before[base.len()..]) {
+            return NewLine {
+                indent: format!("{base}{marker}"),
+                closing_indent: None,
+            };
+        }
+    }
+
+    let trimmed = before.trim_end();
+    let Some(opener) = openers.iter().find(|opener| trimmed.ends_with(opener.as_str())) else {
+        return NewLine {
+            indent: base.to_owned(),
+            closing_indent: None,
+        };
+    };
+
+    let closes = match opener.as_str() {
+        "{" => after.trim_start().starts_with('}'),
+        "[" => after.trim_start().starts_with(']'),
+        "(" => after.trim_start().starts_with(')'),
+        _ => false,
+    };
+
+    NewLine {
+        indent: format!("{base}{unit}"),
+        closing_indent: closes.then(|| base.to_owned()),
+    }
+}
+
+/// The amount of characters to remove before a closing bracket typed at the start of a line
+pub fn dedent(before: &str, unit: &str) -> usize {
+    if before.is_empty() || !before.chars().all(char::is_whitespace) {
+        return 0;
+    }
+
+    if before.ends_with('\t') {
+        return 1;
+    }
+
+    // Remove spaces back to the previous level
+    let spaces = before.len() - before.trim_end_matches(' ').len();
+    match spaces % unit.len().max(1) {
+        0 => spaces.min(unit.len()),
+        rest => rest,
+    }
+}
+
+/// The whitespace a line starts with
+pub fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// The marker that continues a Markdown list item: `- `, `* `, `+ `, the next number of `1. ` or a task box
+fn list_marker(line: &str) -> Option<String> {
+    for bullet in ["- [ ] ", "- [x] ", "* [ ] ", "* [x] "] {
+        if line.starts_with(bullet) {
+            return Some(format!("{}[ ] ", &bullet[..2]));
+        }
+    }
+
+    for bullet in ["- ", "* ", "+ "] {
+        if line.starts_with(bullet) {
+            return Some(bullet.to_owned());
+        }
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        let number: u64 = line[..digits].parse().ok()?;
+        return Some(format!("{}{}", number + 1, &rest[..2]));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn openers(language: &str) -> Vec<String> {
+        default_openers(language).iter().map(|opener| opener.to_string()).collect()
+    }
+
+    fn indent(before: &str, after: &str, language: &str) -> NewLine {
+        new_line(before, after, language, &openers(language), "    ")
+    }
+
+    #[test]
+    fn new_lines_keep_the_indentation() {
+        assert_eq!(indent("    let a = 1;", "", "rs").indent, "    ");
+        assert_eq!(indent("\tfoo", "", "rs").indent, "\t");
+        assert_eq!(indent("", "", "rs").indent, "");
+    }
+
+    #[test]
+    fn new_lines_after_an_opener_are_indented() {
+        assert_eq!(indent("  fn main() {", "", "rs"), NewLine { indent: "      ".to_owned(), closing_indent: None });
+        assert_eq!(indent("if a:  ", "", "py").indent, "    ");
+        assert_eq!(indent("if a:", "", "rs").indent, "");
+    }
+
+    #[test]
+    fn a_closing_bracket_after_the_cursor_gets_its_own_line() {
+        let new_line = indent("  call(", ")", "rs");
+        assert_eq!(new_line.indent, "      ");
+        assert_eq!(new_line.closing_indent.as_deref(), Some("  "));
+
+        assert_eq!(indent("  call(", "]", "rs").closing_indent, None);
+    }
+
+    #[test]
+    fn markdown_lists_are_continued() {
+        assert_eq!(indent("  - item", "", "md").indent, "  - ");
+        assert_eq!(indent("* [x] done", "", "markdown").indent, "* [ ] ");
+        assert_eq!(indent("9. ninth", "", "md").indent, "10. ");
+        assert_eq!(indent("1) first", "", "md").indent, "2) ");
+        assert_eq!(indent("2026 was a year", "", "md").indent, "");
+        assert_eq!(indent("- item", "", "txt").indent, "");
+    }
+
+    #[test]
+    fn closing_brackets_remove_a_level() {
+        assert_eq!(dedent("        ", "    "), 4);
+        assert_eq!(dedent("      ", "    "), 2);
+        assert_eq!(dedent("  ", "    "), 2);
+        assert_eq!(dedent("\t\t", "\t"), 1);
+        assert_eq!(dedent("", "    "), 0);
+        assert_eq!(dedent("  foo", "    "), 0);
+    }
+
+    #[test]
+    fn leading_whitespace_is_found() {
+        assert_eq!(leading_whitespace(" \t x "), " \t ");
+        assert_eq!(leading_whitespace("x"), "");
+    }
+
+    const SPACES: Indentation = Indentation { tab_width: 4, insert_spaces: true };
+    const TABS: Indentation = Indentation { tab_width: 4, insert_spaces: false };
+
+    #[test]
+    fn tab_inserts_up_to_the_next_tab_stop() {
+        assert_eq!(SPACES.tab_at(0), "    ");
+        assert_eq!(SPACES.tab_at(5), "   ");
+        assert_eq!(TABS.tab_at(5), "\t");
+        assert_eq!(SPACES.unit(), "    ");
+        assert_eq!(TABS.unit(), "\t");
+    }
+
+    #[test]
+    fn tabs_reach_to_the_next_tab_stop() {
+        assert_eq!(SPACES.width("\t"), 4);
+        assert_eq!(SPACES.width("ab\tc"), 5);
+        assert_eq!(SPACES.width("    \t"), 8);
+    }
+
+    #[test]
+    fn indentation_is_converted() {
+        assert_eq!(SPACES.convert("\t\tx\ty"), "        x\ty");
+        assert_eq!(TABS.convert("      x"), "\t  x");
+        assert_eq!(TABS.convert(" \tx"), "\tx");
+        assert_eq!(TABS.convert("x"), "x");
+    }
+
+    #[test]
+    fn outdenting_removes_a_tab_or_up_to_a_level_of_spaces() {
+        assert_eq!(SPACES.outdent("\t    x"), 1);
+        assert_eq!(SPACES.outdent("      x"), 4);
+        assert_eq!(SPACES.outdent("  x"), 2);
+        assert_eq!(SPACES.outdent("x"), 0);
+    }
+
+    #[test]
+    fn spaces_are_detected_by_the_most_common_step() {
+        let text = "a {\n  b {\n    c\n  }\n  d\n}\n";
+        assert_eq!(detect(text, 8), Some(Indentation { tab_width: 2, insert_spaces: true }));
+
+        let text = "a:\n    b:\n        c\n    d\n";
+        assert_eq!(detect(text, 8), Some(Indentation { tab_width: 4, insert_spaces: true }));
+    }
+
+    #[test]
+    fn tabs_are_detected_with_the_configured_width() {
+        let text = "a {\n\tb\n\tc\n    d\n}\n";
+        assert_eq!(detect(text, 8), Some(Indentation { tab_width: 8, insert_spaces: false }));
+    }
+
+    #[test]
+    fn unindented_text_is_not_detected() {
+        assert_eq!(detect("a\nb\n\n   \nc\n", 4), None);
+        assert_eq!(detect("", 4), None);
+    }
+}
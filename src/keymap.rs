@@ -0,0 +1,592 @@
+//! The keymap maps key chords to editor commands. Every command has a default chord, which can be
+//! changed or removed through the `keybindings` section of the settings file.
+
+use iced::keyboard::{self, key, Modifiers};
+use iced::widget::text_editor::KeyPress;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::editor::Message;
+use crate::lines;
+
+/// The amount the font size changes with every zoom step
+const ZOOM_STEP: f32 = 1.0;
+
+/// A command of the editor that can be bound to a key chord
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    /// Create a new file
+    NewFile,
+    /// Open a file
+    OpenFile,
+    /// Save the file
+    SaveFile,
+    /// Open the "go to line" input
+    GoToLine,
+    /// Set the language of the document
+    SetLanguage,
+    /// Show or hide the window
+    ToggleWindow,
+    /// Open the command palette
+    OpenPalette,
+    /// Turn word wrapping on or off
+    ToggleWordWrap,
+    /// Switch to the next highlighting theme
+    NextTheme,
+    /// Make the text bigger
+    ZoomIn,
+    /// Make the text smaller
+    ZoomOut,
+    /// Reset the text to the configured size
+    ZoomReset,
+    /// Allow or block editing the document
+    ToggleReadOnly,
+    /// Follow the file as it grows, like `tail -f`
+    ToggleFollow,
+    /// Convert the indentation of every line to spaces
+    ConvertToSpaces,
+    /// Convert the indentation of every line to tabs
+    ConvertToTabs,
+    /// Move the cursor to the bracket matching the one next to it
+    JumpToBracket,
+    /// Duplicate the selected lines, or the selection inside a line
+    DuplicateLines,
+    /// Move the selected lines up
+    MoveLinesUp,
+    /// Move the selected lines down
+    MoveLinesDown,
+    /// Delete the selected lines
+    DeleteLines,
+    /// Join the selected lines, or the line with the next one
+    JoinLines,
+    /// Sort the selected lines, or all lines, ascending
+    SortLines,
+    /// Sort the selected lines, or all lines, descending
+    SortLinesDescending,
+    /// Sort the selected lines, or all lines, by the number they start with
+    SortLinesNumeric,
+    /// Sort the selected lines, or all lines, ignoring the case
+    SortLinesCaseInsensitive,
+    /// Remove repeated lines from the selected lines, or all lines
+    UniqueLines,
+    /// Reverse the order of the selected lines, or all lines
+    ReverseLines,
+    /// Comment out the selected lines, or uncomment them
+    ToggleComment,
+    /// Wrap the selection in a block comment, or unwrap it
+    ToggleBlockComment,
+    /// Undo the last edit
+    Undo,
+    /// Redo the last undone edit
+    Redo,
+    /// Set the line ending the document is saved with
+    SetLineEnding,
+    /// Set the character set the document is saved in
+    SetCharset,
+}
+
+impl Command {
+    /// All the commands, in the order they are presented to the user
+    pub const ALL: &'static [Command] = &[
+        Command::NewFile,
+        Command::OpenFile,
+        Command::SaveFile,
+        Command::GoToLine,
+        Command::SetLanguage,
+        Command::ToggleWindow,
+        Command::OpenPalette,
+        Command::ToggleWordWrap,
+        Command::NextTheme,
+        Command::ZoomIn,
+        Command::ZoomOut,
+        Command::ZoomReset,
+        Command::ToggleReadOnly,
+        Command::ToggleFollow,
+        Command::ConvertToSpaces,
+        Command::ConvertToTabs,
+        Command::JumpToBracket,
+        Command::DuplicateLines,
+        Command::MoveLinesUp,
+        Command::MoveLinesDown,
+        Command::DeleteLines,
+        Command::JoinLines,
+        Command::SortLines,
+        Command::SortLinesDescending,
+        Command::SortLinesNumeric,
+        Command::SortLinesCaseInsensitive,
+        Command::UniqueLines,
+        Command::ReverseLines,
+        Command::ToggleComment,
+        Command::ToggleBlockComment,
+        Command::Undo,
+        Command::Redo,
+        Command::SetLineEnding,
+        Command::SetCharset,
+    ];
+
+    /// The name of the command, as used in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::NewFile => "new_file",
+            Command::OpenFile => "open_file",
+            Command::SaveFile => "save_file",
+            Command::GoToLine => "go_to_line",
+            Command::SetLanguage => "set_language",
+            Command::ToggleWindow => "toggle_window",
+            Command::OpenPalette => "command_palette",
+            Command::ToggleWordWrap => "toggle_word_wrap",
+            Command::NextTheme => "next_theme",
+            Command::ZoomIn => "zoom_in",
+            Command::ZoomOut => "zoom_out",
+            Command::ZoomReset => "zoom_reset",
+            Command::ToggleReadOnly => "toggle_read_only",
+            Command::ToggleFollow => "toggle_follow",
+            Command::ConvertToSpaces => "convert_to_spaces",
+            Command::ConvertToTabs => "convert_to_tabs",
+            Command::JumpToBracket => "jump_to_bracket",
+            Command::DuplicateLines => "duplicate_lines",
+            Command::MoveLinesUp => "move_lines_up",
+            Command::MoveLinesDown => "move_lines_down",
+            Command::DeleteLines => "delete_lines",
+            Command::JoinLines => "join_lines",
+            Command::SortLines => "sort_lines",
+            Command::SortLinesDescending => "sort_lines_descending",
+            Command::SortLinesNumeric => "sort_lines_numeric",
+            Command::SortLinesCaseInsensitive => "sort_lines_case_insensitive",
+            Command::UniqueLines => "unique_lines",
+            Command::ReverseLines => "reverse_lines",
+            Command::ToggleComment => "toggle_comment",
+            Command::ToggleBlockComment => "toggle_block_comment",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::SetLineEnding => "set_line_ending",
+            Command::SetCharset => "set_charset",
+        }
+    }
+
+    /// The title of the command, as shown in the command palette
+    pub fn title(self) -> &'static str {
+        match self {
+            Command::NewFile => "New file",
+            Command::OpenFile => "Open file",
+            Command::SaveFile => "Save file",
+            Command::GoToLine => "Go to line",
+            Command::SetLanguage => "Set language",
+            Command::ToggleWindow => "Toggle window",
+            Command::OpenPalette => "Command palette",
+            Command::ToggleWordWrap => "Toggle word wrap",
+            Command::NextTheme => "Next theme",
+            Command::ZoomIn => "Zoom in",
+            Command::ZoomOut => "Zoom out",
+            Command::ZoomReset => "Reset zoom",
+            Command::ToggleReadOnly => "Toggle read-only",
+            Command::ToggleFollow => "Toggle follow",
+            Command::ConvertToSpaces => "Convert indentation to spaces",
+            Command::ConvertToTabs => "Convert indentation to tabs",
+            Command::JumpToBracket => "Jump to matching bracket",
+            Command::DuplicateLines => "Duplicate lines",
+            Command::MoveLinesUp => "Move lines up",
+            Command::MoveLinesDown => "Move lines down",
+            Command::DeleteLines => "Delete lines",
+            Command::JoinLines => "Join lines",
+            Command::SortLines => "Sort lines ascending",
+            Command::SortLinesDescending => "Sort lines descending",
+            Command::SortLinesNumeric => "Sort lines by number",
+            Command::SortLinesCaseInsensitive => "Sort lines ignoring case",
+            Command::UniqueLines => "Remove duplicate lines",
+            Command::ReverseLines => "Reverse lines",
+            Command::ToggleComment => "Toggle line comment",
+            Command::ToggleBlockComment => "Toggle block comment",
+            Command::Undo => "Undo",
+            Command::Redo => "Redo",
+            Command::SetLineEnding => "Set line ending",
+            Command::SetCharset => "Set charset",
+        }
+    }
+
+    /// Finds a command by the name used in the settings file
+    pub fn from_name(name: &str) -> Option<Self> {
+        Command::ALL
+            .iter()
+            .copied()
+            .find(|command| command.name() == name)
+    }
+
+    /// The message that is sent to the editor when the command is triggered
+    pub fn message(self) -> Message {
+        match self {
+            Command::NewFile => Message::NewFile,
+            Command::OpenFile => Message::OpenFile,
+            Command::SaveFile => Message::SaveFile,
+            Command::GoToLine => Message::GoToLine,
+            Command::SetLanguage => Message::SetLanguage,
+            Command::ToggleWindow => Message::ToggleWindow,
+            Command::OpenPalette => Message::CommandPalette,
+            Command::ToggleWordWrap => Message::ToggleWordWrap,
+            Command::NextTheme => Message::NextTheme,
+            Command::ZoomIn => Message::Zoom(ZOOM_STEP),
+            Command::ZoomOut => Message::Zoom(-ZOOM_STEP),
+            Command::ZoomReset => Message::ZoomReset,
+            Command::ToggleReadOnly => Message::ToggleReadOnly,
+            Command::ToggleFollow => Message::ToggleFollow,
+            Command::ConvertToSpaces => Message::ConvertIndentation(true),
+            Command::ConvertToTabs => Message::ConvertIndentation(false),
+            Command::JumpToBracket => Message::JumpToBracket,
+            Command::DuplicateLines => Message::EditLines(lines::Operation::Duplicate),
+            Command::MoveLinesUp => Message::EditLines(lines::Operation::MoveUp),
+            Command::MoveLinesDown => Message::EditLines(lines::Operation::MoveDown),
+            Command::DeleteLines => Message::EditLines(lines::Operation::Delete),
+            Command::JoinLines => Message::EditLines(lines::Operation::Join),
+            Command::SortLines => Message::EditLines(lines::Operation::Sort(lines::Order::Ascending)),
+            Command::SortLinesDescending => Message::EditLines(lines::Operation::Sort(lines::Order::Descending)),
+            Command::SortLinesNumeric => Message::EditLines(lines::Operation::Sort(lines::Order::Numeric)),
+            Command::SortLinesCaseInsensitive => Message::EditLines(lines::Operation::Sort(lines::Order::CaseInsensitive)),
+            Command::UniqueLines => Message::EditLines(lines::Operation::Unique),
+            Command::ReverseLines => Message::EditLines(lines::Operation::Reverse),
+            Command::ToggleComment => Message::ToggleComment(false),
+            Command::ToggleBlockComment => Message::ToggleComment(true),
+            Command::Undo => Message::Undo,
+            Command::Redo => Message::Redo,
+            Command::SetLineEnding => Message::SetLineEnding,
+            Command::SetCharset => Message::SetCharset,
+        }
+    }
+
+    /// The chord the command is bound to if the user does not change it
+    fn default_chord(self) -> Option<&'static str> {
+        match self {
+            Command::NewFile => Some("primary+n"),
+            Command::OpenFile => Some("primary+o"),
+            Command::SaveFile => Some("primary+s"),
+            Command::GoToLine => Some("primary+g"),
+            Command::SetLanguage => None,
+            // The window is toggled with the global hotkey by default
+            Command::ToggleWindow => None,
+            Command::OpenPalette => Some("primary+shift+p"),
+            Command::ToggleWordWrap => Some("alt+z"),
+            Command::NextTheme => None,
+            Command::ZoomIn => Some("primary+="),
+            Command::ZoomOut => Some("primary+-"),
+            Command::ZoomReset => Some("primary+0"),
+            Command::ToggleReadOnly => None,
+            Command::ToggleFollow => None,
+            Command::ConvertToSpaces => None,
+            Command::ConvertToTabs => None,
+            Command::JumpToBracket => Some("primary+m"),
+            Command::DuplicateLines => Some("primary+shift+d"),
+            Command::MoveLinesUp => Some("alt+up"),
+            Command::MoveLinesDown => Some("alt+down"),
+            Command::DeleteLines => Some("primary+shift+k"),
+            Command::JoinLines => Some("primary+j"),
+            Command::SortLines => None,
+            Command::SortLinesDescending => None,
+            Command::SortLinesNumeric => None,
+            Command::SortLinesCaseInsensitive => None,
+            Command::UniqueLines => None,
+            Command::ReverseLines => None,
+            Command::ToggleComment => Some("primary+/"),
+            // The key of a chord is the one without modifiers, so Shift+/ is bound as `/`, not `?`
+            Command::ToggleBlockComment => Some("primary+shift+/"),
+            Command::Undo => Some("primary+z"),
+            Command::Redo => Some("primary+shift+z"),
+            Command::SetLineEnding => None,
+            Command::SetCharset => None,
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A key combined with a set of modifiers, e.g. `ctrl+shift+p`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    /// The key without modifiers applied
+    key: keyboard::Key,
+    /// The modifiers that have to be held down
+    modifiers: Modifiers,
+}
+
+impl Chord {
+    /// Parses a chord like `ctrl+shift+p`. The modifier `primary` stands for `ctrl`,
+    /// or `cmd` on macOS.
+    pub fn parse(chord: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+
+        // Allow binding the plus key itself, e.g. `ctrl++`
+        if chord.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+
+        let key = parts.pop().filter(|key| !key.is_empty()).ok_or(format!("Missing key in chord: {chord}"))?;
+
+        let mut modifiers = Modifiers::empty();
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "primary" => Modifiers::COMMAND,
+                "ctrl" | "control" => Modifiers::CTRL,
+                "shift" => Modifiers::SHIFT,
+                "alt" | "option" => Modifiers::ALT,
+                "cmd" | "command" | "super" | "logo" | "meta" => Modifiers::LOGO,
+                _ => return Err(format!("Unknown modifier in chord: {chord}")),
+            };
+        }
+
+        Ok(Chord {
+            key: parse_key(key).ok_or(format!("Unknown key in chord: {chord}"))?,
+            modifiers,
+        })
+    }
+
+    /// Whether the key press matches this chord
+    pub fn matches(&self, key_press: &KeyPress) -> bool {
+        let key = match key_press.key.as_ref() {
+            keyboard::Key::Character(c) => keyboard::Key::Character(c.to_lowercase().into()),
+            _ => key_press.key.clone(),
+        };
+
+        key == self.key && key_press.modifiers == self.modifiers
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.control() {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.alt() {
+            f.write_str(if cfg!(target_os = "macos") { "Option+" } else { "Alt+" })?;
+        }
+        if self.modifiers.shift() {
+            f.write_str("Shift+")?;
+        }
+        if self.modifiers.logo() {
+            f.write_str(if cfg!(target_os = "macos") { "Cmd+" } else { "Super+" })?;
+        }
+
+        match &self.key {
+            keyboard::Key::Character(c) => f.write_str(&c.to_uppercase()),
+            keyboard::Key::Named(named) => write!(f, "{named:?}"),
+            keyboard::Key::Unidentified => f.write_str("?"),
+        }
+    }
+}
+
+/// Parses the key part of a chord
+fn parse_key(key: &str) -> Option<keyboard::Key> {
+    let named = match key.to_lowercase().as_str() {
+        "enter" | "return" => key::Named::Enter,
+        "tab" => key::Named::Tab,
+        "space" => key::Named::Space,
+        "escape" | "esc" => key::Named::Escape,
+        "backspace" => key::Named::Backspace,
+        "delete" | "del" => key::Named::Delete,
+        "insert" => key::Named::Insert,
+        "up" => key::Named::ArrowUp,
+        "down" => key::Named::ArrowDown,
+        "left" => key::Named::ArrowLeft,
+        "right" => key::Named::ArrowRight,
+        "home" => key::Named::Home,
+        "end" => key::Named::End,
+        "pageup" => key::Named::PageUp,
+        "pagedown" => key::Named::PageDown,
+        "f1" => key::Named::F1,
+        "f2" => key::Named::F2,
+        "f3" => key::Named::F3,
+        "f4" => key::Named::F4,
+        "f5" => key::Named::F5,
+        "f6" => key::Named::F6,
+        "f7" => key::Named::F7,
+        "f8" => key::Named::F8,
+        "f9" => key::Named::F9,
+        "f10" => key::Named::F10,
+        "f11" => key::Named::F11,
+        "f12" => key::Named::F12,
+        other if other.chars().count() == 1 => {
+            return Some(keyboard::Key::Character(other.into()));
+        }
+        _ => return None,
+    };
+
+    Some(keyboard::Key::Named(named))
+}
+
+/// The keymap holds the chord of every bound command
+#[derive(Debug)]
+pub struct Keymap {
+    /// The bound chords and the command they trigger
+    bindings: Vec<(Chord, Command)>,
+}
+
+impl Keymap {
+    /// Creates the keymap from the default chords and the overrides from the settings file.
+    /// An empty chord in the overrides removes the binding of a command.
+    pub fn new(overrides: &HashMap<String, String>) -> Self {
+        let mut chords: Vec<(Command, Option<String>)> = Command::ALL
+            .iter()
+            .map(|command| (*command, command.default_chord().map(str::to_owned)))
+            .collect();
+
+        for (name, chord) in overrides {
+            match Command::from_name(name) {
+                Some(command) => {
+                    if let Some(entry) = chords.iter_mut().find(|(c, _)| *c == command) {
+                        entry.1 = Some(chord.clone()).filter(|chord| !chord.trim().is_empty());
+                    }
+                }
+                None => log::warn!("Unknown command in key bindings: {}", name),
+            }
+        }
+
+        let mut keymap = Keymap { bindings: Vec::new() };
+
+        for (command, chord) in chords {
+            let Some(chord) = chord else { continue };
+
+            let chord = match Chord::parse(&chord) {
+                Ok(chord) => chord,
+                Err(error) => {
+                    log::warn!("Ignoring key binding of {}: {}", command, error);
+                    continue;
+                }
+            };
+
+            // User overrides win over the defaults of other commands
+            let is_override = overrides.contains_key(command.name());
+            if let Some(index) = keymap.bindings.iter().position(|(c, _)| *c == chord) {
+                let other = keymap.bindings[index].1;
+                let other_is_override = overrides.contains_key(other.name());
+
+                if is_override && !other_is_override {
+                    log::warn!("Key binding {} of {} replaces the one of {}", chord, command, other);
+                    keymap.bindings.remove(index);
+                } else {
+                    log::warn!("Key binding {} of {} conflicts with {}, ignoring it", chord, command, other);
+                    continue;
+                }
+            }
+
+            keymap.bindings.push((chord, command));
+        }
+
+        log::debug!("Loaded keymap: {:?}", keymap.bindings);
+        keymap
+    }
+
+    /// The command bound to the key press, if any
+    pub fn command(&self, key_press: &KeyPress) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|(chord, _)| chord.matches(key_press))
+            .map(|(_, command)| *command)
+    }
+
+    /// The chord a command is bound to, if any
+    pub fn chord(&self, command: Command) -> Option<&Chord> {
+        self.bindings
+            .iter()
+            .find(|(_, c)| *c == command)
+            .map(|(chord, _)| chord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::widget::text_editor::Status;
+
+    fn key_press(key: keyboard::Key, modifiers: Modifiers) -> KeyPress {
+        KeyPress { key, modifiers, text: None, status: Status::Focused }
+    }
+
+    fn character(c: &str) -> keyboard::Key {
+        keyboard::Key::Character(c.into())
+    }
+
+    #[test]
+    fn chords_are_parsed() {
+        let chord = Chord::parse("Ctrl + Shift + P").unwrap();
+        assert_eq!(chord, Chord { key: character("p"), modifiers: Modifiers::CTRL | Modifiers::SHIFT });
+
+        let chord = Chord::parse("alt+pageup").unwrap();
+        assert_eq!(chord, Chord { key: keyboard::Key::Named(key::Named::PageUp), modifiers: Modifiers::ALT });
+
+        assert_eq!(Chord::parse("primary+=").unwrap().modifiers, Modifiers::COMMAND);
+    }
+
+    #[test]
+    fn the_plus_key_can_be_bound() {
+        assert_eq!(Chord::parse("ctrl++").unwrap().key, character("+"));
+    }
+
+    #[test]
+    fn invalid_chords_are_rejected() {
+        assert!(Chord::parse("").is_err());
+        assert!(Chord::parse("ctrl+").is_err());
+        assert!(Chord::parse("hyper+p").is_err());
+        assert!(Chord::parse("ctrl+pp").is_err());
+    }
+
+    #[test]
+    fn key_presses_match_without_case_and_with_the_exact_modifiers() {
+        let chord = Chord::parse("ctrl+shift+p").unwrap();
+        assert!(chord.matches(&key_press(character("P"), Modifiers::CTRL | Modifiers::SHIFT)));
+        assert!(!chord.matches(&key_press(character("p"), Modifiers::CTRL)));
+        assert!(!chord.matches(&key_press(character("p"), Modifiers::CTRL | Modifiers::SHIFT | Modifiers::ALT)));
+    }
+
+    #[test]
+    fn the_block_comment_is_toggled_with_shift_slash() {
+        let keymap = Keymap::new(&HashMap::new());
+        let shift_slash = key_press(character("/"), Modifiers::COMMAND | Modifiers::SHIFT);
+        let slash = key_press(character("/"), Modifiers::COMMAND);
+
+        assert_eq!(keymap.command(&shift_slash), Some(Command::ToggleBlockComment));
+        assert_eq!(keymap.command(&slash), Some(Command::ToggleComment));
+    }
+
+    #[test]
+    fn the_default_chords_do_not_conflict() {
+        let keymap = Keymap::new(&HashMap::new());
+
+        for command in Command::ALL {
+            if command.default_chord().is_some() {
+                assert!(keymap.chord(*command).is_some(), "{command} lost its default chord");
+            }
+        }
+    }
+
+    #[test]
+    fn overrides_replace_conflicting_defaults() {
+        let overrides = HashMap::from([("zoom_in".to_owned(), "primary+s".to_owned())]);
+        let keymap = Keymap::new(&overrides);
+
+        assert_eq!(keymap.chord(Command::ZoomIn), Some(&Chord::parse("primary+s").unwrap()));
+        assert_eq!(keymap.chord(Command::SaveFile), None);
+    }
+
+    #[test]
+    fn conflicting_overrides_keep_the_first_binding() {
+        let overrides = HashMap::from([
+            ("zoom_in".to_owned(), "f5".to_owned()),
+            ("zoom_out".to_owned(), "f5".to_owned()),
+        ]);
+        let keymap = Keymap::new(&overrides);
+
+        let bound: Vec<Command> = [Command::ZoomIn, Command::ZoomOut]
+            .into_iter()
+            .filter(|command| keymap.chord(*command).is_some())
+            .collect();
+        assert_eq!(bound.len(), 1);
+    }
+
+    #[test]
+    fn empty_overrides_unbind_commands() {
+        let overrides = HashMap::from([("save_file".to_owned(), " ".to_owned())]);
+        assert_eq!(Keymap::new(&overrides).chord(Command::SaveFile), None);
+    }
+}
@@ -0,0 +1,211 @@
+//! Detection of the language of a document, which decides the syntax used for highlighting.
+//! The language is taken from, in order: a modeline (`vim: ft=python` or `-*- mode: python -*-`),
+//! a well known file name (`Dockerfile`, `.bashrc`, ...), the file extension and the shebang line.
+//! The result is a token the highlighter understands, usually a file extension.
+
+use std::ffi;
+use std::path::Path;
+
+/// The language used when nothing else matches
+pub static PLAIN_TEXT: &str = "txt";
+
+/// The amount of lines at the start and the end of a document that are searched for modelines
+pub const HEAD_LINES: usize = 5;
+
+/// Well known file names and the language they are written in
+const FILE_NAMES: &[(&str, &str)] = &[
+    ("Dockerfile", "dockerfile"),
+    ("Containerfile", "dockerfile"),
+    ("Makefile", "makefile"),
+    ("makefile", "makefile"),
+    ("GNUmakefile", "makefile"),
+    ("CMakeLists.txt", "cmake"),
+    ("Rakefile", "rb"),
+    ("Gemfile", "rb"),
+    ("Vagrantfile", "rb"),
+    ("Jenkinsfile", "groovy"),
+    ("Cargo.lock", "toml"),
+    (".bashrc", "sh"),
+    (".bash_profile", "sh"),
+    (".bash_aliases", "sh"),
+    (".bash_logout", "sh"),
+    (".profile", "sh"),
+    (".zshrc", "sh"),
+    (".zprofile", "sh"),
+    (".zshenv", "sh"),
+    (".vimrc", "vim"),
+];
+
+/// Interpreters named in shebang lines and the language they run
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("bash", "sh"),
+    ("sh", "sh"),
+    ("zsh", "sh"),
+    ("dash", "sh"),
+    ("ksh", "sh"),
+    ("python", "py"),
+    ("node", "js"),
+    ("perl", "pl"),
+    ("ruby", "rb"),
+    ("php", "php"),
+    ("lua", "lua"),
+    ("Rscript", "r"),
+    ("tclsh", "tcl"),
+];
+
+/// Detects the language of a document from its path and its text
+pub fn detect(path: Option<&Path>, text: &str) -> String {
+    let language = modeline(text)
+        .or_else(|| path.and_then(file_name))
+        .or_else(|| path.and_then(extension))
+        .or_else(|| text.lines().next().and_then(shebang))
+        .unwrap_or(PLAIN_TEXT);
+
+    log::debug!("Detected language: {}", language);
+    language.to_owned()
+}
+
+/// Looks up the language of a well known file name
+fn file_name(path: &Path) -> Option<&'static str> {
+    let name = path.file_name().and_then(ffi::OsStr::to_str)?;
+
+    FILE_NAMES
+        .iter()
+        .find(|(file_name, _)| *file_name == name)
+        .map(|(_, language)| *language)
+}
+
+/// The file extension, which the highlighter understands directly
+fn extension(path: &Path) -> Option<&str> {
+    path.extension().and_then(ffi::OsStr::to_str)
+}
+
+/// Reads the interpreter of a shebang line like `#!/usr/bin/env python3`
+fn shebang(line: &str) -> Option<&'static str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+
+    if program == "env" {
+        // Skip the options of env, e.g. `env -S deno run`
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    // Strip version suffixes like `python3.12`
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    INTERPRETERS
+        .iter()
+        .find(|(interpreter, _)| *interpreter == program)
+        .map(|(_, language)| *language)
+}
+
+/// Searches the first and last lines of the text for a vim or emacs modeline
+fn modeline(text: &str) -> Option<&str> {
+    let lines: Vec<&str> = text.lines().collect();
+    let tail = lines.len().saturating_sub(HEAD_LINES).max(HEAD_LINES.min(lines.len()));
+
+    lines
+        .iter()
+        .take(HEAD_LINES)
+        .chain(&lines[tail..])
+        .find_map(|line| vim_modeline(line).or_else(|| emacs_modeline(line)))
+}
+
+/// Parses a vim modeline like `# vim: set ft=python ts=4:`
+fn vim_modeline(line: &str) -> Option<&str> {
+    // The marker starts the line or follows whitespace, so words like `regex:` don't count
+    let start = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(index, _)| line[..*index].chars().next_back().is_none_or(char::is_whitespace))
+            .map(|(index, _)| index + marker.len())
+    })?;
+
+    line[start..]
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .find_map(|option| {
+            ["ft=", "filetype=", "syntax=", "syn="]
+                .iter()
+                .find_map(|key| option.strip_prefix(key))
+        })
+        .filter(|language| !language.is_empty())
+}
+
+/// Parses an emacs modeline like `-*- mode: python -*-` or `-*- python -*-`
+fn emacs_modeline(line: &str) -> Option<&str> {
+    let start = line.find("-*-")? + 3;
+    let end = line[start..].find("-*-")? + start;
+    let variables = line[start..end].trim();
+
+    if !variables.contains(':') {
+        return Some(variables).filter(|language| !language.is_empty());
+    }
+
+    variables.split(';').find_map(|variable| {
+        let (key, value) = variable.split_once(':')?;
+        (key.trim().eq_ignore_ascii_case("mode")).then_some(value.trim())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect_path(path: &str, text: &str) -> String {
+        detect(Some(Path::new(path)), text)
+    }
+
+    #[test]
+    fn detects_well_known_file_names() {
+        assert_eq!(detect_path("/project/Dockerfile", ""), "dockerfile");
+        assert_eq!(detect_path("Containerfile", ""), "dockerfile");
+        assert_eq!(detect_path("CMakeLists.txt", ""), "cmake");
+        assert_eq!(detect_path("/home/user/.bashrc", ""), "sh");
+        assert_eq!(detect_path("Cargo.lock", ""), "toml");
+    }
+
+    #[test]
+    fn detects_extensions() {
+        assert_eq!(detect_path("main.rs", ""), "rs");
+        assert_eq!(detect_path("notes", ""), PLAIN_TEXT);
+        assert_eq!(detect(None, ""), PLAIN_TEXT);
+    }
+
+    #[test]
+    fn detects_shebangs() {
+        assert_eq!(detect_path("run", "#!/bin/bash\necho"), "sh");
+        assert_eq!(detect_path("run", "#!/usr/bin/env python3.12\n"), "py");
+        assert_eq!(detect_path("run", "#!/usr/bin/env -S node --flag\n"), "js");
+        assert_eq!(detect_path("run", "#!/usr/bin/unknown\n"), PLAIN_TEXT);
+        // The extension wins over the shebang
+        assert_eq!(detect_path("run.rb", "#!/bin/sh\n"), "rb");
+    }
+
+    #[test]
+    fn detects_vim_modelines() {
+        assert_eq!(detect_path("a.txt", "# vim: set ft=python ts=4:"), "python");
+        assert_eq!(detect_path("a.txt", "vim:filetype=lua"), "lua");
+        assert_eq!(detect_path("a.txt", "/* vi: syntax=c */"), "c");
+
+        let tail = format!("{}// vim: ft=rs", "line\n".repeat(20));
+        assert_eq!(detect_path("a.txt", &tail), "rs");
+    }
+
+    #[test]
+    fn ignores_modeline_markers_inside_words() {
+        assert_eq!(detect_path("a.txt", "regex: ft=python"), "txt");
+        assert_eq!(detect_path("a.txt", "gvim: ft=python"), "txt");
+        assert_eq!(detect_path("a.txt", "complex: syntax=c"), "txt");
+    }
+
+    #[test]
+    fn detects_emacs_modelines() {
+        assert_eq!(detect_path("a.txt", "# -*- mode: ruby; coding: utf-8 -*-"), "ruby");
+        assert_eq!(detect_path("a.txt", "// -*- c++ -*-"), "c++");
+    }
+
+    #[test]
+    fn modelines_only_count_near_the_start_and_end() {
+        let middle = format!("{}# vim: ft=python\n{}", "line\n".repeat(10), "line\n".repeat(10));
+        assert_eq!(detect_path("a.txt", &middle), "txt");
+    }
+}
@@ -0,0 +1,142 @@
+//! Files above the `large_file_threshold` are not loaded at once. Their lines are indexed in the background,
+//! and only a window of lines around the part the user looks at is read into the editor.
+//! Highlighting, word wrapping and editing are turned off for them.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The amount of lines shown at once
+pub const WINDOW_LINES: usize = 10_000;
+/// The amount of lines between two indexed offsets
+const INDEX_STRIDE: usize = 1_000;
+/// The size of the buffer used to read the file
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// A file that is too large to be loaded at once
+#[derive(Debug, Clone)]
+pub struct LargeFile {
+    /// The path of the file
+    path: PathBuf,
+    /// The size of the file in bytes
+    size: u64,
+    /// The amount of lines of the file
+    line_count: usize,
+    /// The byte offset of every [`INDEX_STRIDE`]th line
+    offsets: Arc<Vec<u64>>,
+    /// The first line of the window shown in the editor
+    first_line: usize,
+}
+
+impl LargeFile {
+    /// Indexes the lines of a file
+    pub async fn index(path: PathBuf) -> io::Result<Self> {
+        tokio::task::spawn_blocking(move || {
+            let mut file = File::open(&path)?;
+            let size = file.metadata()?.len();
+            let mut buffer = vec![0; BUFFER_SIZE];
+            let mut offsets = vec![0];
+            let mut line_count = 1;
+            let mut position = 0;
+
+            loop {
+                let read = file.read(&mut buffer)?;
+                if read == 0 {
+                    break;
+                }
+
+                for (index, _) in buffer[..read].iter().enumerate().filter(|(_, byte)| **byte == b'\n') {
+                    if line_count % INDEX_STRIDE == 0 {
+                        offsets.push(position + index as u64 + 1);
+                    }
+                    line_count += 1;
+                }
+
+                position += read as u64;
+            }
+
+            log::info!("Indexed {} lines of {:?}", line_count, path);
+
+            Ok(LargeFile {
+                path,
+                size,
+                line_count,
+                offsets: Arc::new(offsets),
+                first_line: 0,
+            })
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Reads the window of lines starting at the given line. Returns the first line of the window and its text.
+    /// Invalid UTF-8 is replaced, line endings are normalized to `\n`.
+    pub async fn read_window(&self, first_line: usize) -> io::Result<(usize, Arc<String>)> {
+        let path = self.path.clone();
+        let offsets = self.offsets.clone();
+        let first_line = first_line.min(self.line_count.saturating_sub(WINDOW_LINES));
+
+        tokio::task::spawn_blocking(move || {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(offsets[first_line / INDEX_STRIDE]))?;
+
+            let mut reader = BufReader::new(file);
+            let mut line = Vec::new();
+
+            for _ in 0..first_line % INDEX_STRIDE {
+                line.clear();
+                reader.read_until(b'\n', &mut line)?;
+            }
+
+            let mut text = String::new();
+            let mut ends_with_break = false;
+
+            for index in 0..WINDOW_LINES {
+                line.clear();
+                if reader.read_until(b'\n', &mut line)? == 0 {
+                    // The empty line after a final line break
+                    if ends_with_break {
+                        text.push('\n');
+                    }
+                    break;
+                }
+
+                ends_with_break = line.last() == Some(&b'\n');
+
+                while line.last().is_some_and(|byte| *byte == b'\n' || *byte == b'\r') {
+                    line.pop();
+                }
+
+                if index > 0 {
+                    text.push('\n');
+                }
+                text.push_str(&String::from_utf8_lossy(&line));
+            }
+
+            Ok((first_line, Arc::new(text)))
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Sets the first line of the window that was read
+    pub fn set_first_line(&mut self, first_line: usize) {
+        self.first_line = first_line;
+    }
+
+    /// The first line of the window shown in the editor
+    pub fn first_line(&self) -> usize {
+        self.first_line
+    }
+
+    /// The amount of lines of the file
+    pub fn line_count(&self) -> usize {
+        self.line_count
+    }
+
+    /// The size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
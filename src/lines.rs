@@ -0,0 +1,141 @@
+//! Operations on whole lines: duplicating, moving, deleting, joining, sorting and so on.
+//! They work on the lines touched by the selection, or on the line of the cursor, and are undone as one step.
+
+use std::cmp::Ordering;
+
+/// An operation on the selected lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Duplicates the lines, or the selection inside a line
+    Duplicate,
+    /// Swaps the lines with the line above them
+    MoveUp,
+    /// Swaps the lines with the line below them
+    MoveDown,
+    /// Deletes the lines
+    Delete,
+    /// Joins the lines into one, or the line with the next one
+    Join,
+    /// Sorts the lines
+    Sort(Order),
+    /// Removes repeated lines, keeping the first of each
+    Unique,
+    /// Reverses the order of the lines
+    Reverse,
+}
+
+impl Operation {
+    /// Whether the operation works on the whole document when less than two lines are selected
+    pub fn applies_to_document(self) -> bool {
+        matches!(self, Operation::Sort(_) | Operation::Unique | Operation::Reverse)
+    }
+}
+
+/// How lines are sorted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Ascending,
+    Descending,
+    /// By the number the line starts with, lines without one last
+    Numeric,
+    /// Alphabetically, ignoring the case
+    CaseInsensitive,
+}
+
+/// Sorts lines in the given order. Lines that compare equal keep their order.
+pub fn sort(mut lines: Vec<String>, order: Order) -> Vec<String> {
+    match order {
+        Order::Ascending => lines.sort(),
+        Order::Descending => lines.sort_by(|a, b| b.cmp(a)),
+        Order::Numeric => lines.sort_by(|a, b| match (leading_number(a), leading_number(b)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }),
+        Order::CaseInsensitive => lines.sort_by_cached_key(|line| line.to_lowercase()),
+    }
+
+    lines
+}
+
+/// Removes repeated lines, keeping the first of each
+pub fn unique(lines: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    lines.into_iter().filter(|line| seen.insert(line.clone())).collect()
+}
+
+/// Joins lines into one, separated by a single space. The indentation of the joined lines is removed.
+pub fn join(lines: &[String]) -> String {
+    let mut joined = lines.first().cloned().unwrap_or_default();
+
+    for line in &lines[1.min(lines.len())..] {
+        let line = line.trim_start();
+        if line.is_empty() {
+            continue;
+        }
+
+        if !joined.is_empty() && !joined.ends_with(char::is_whitespace) {
+            joined.push(' ');
+        }
+        joined.push_str(line);
+    }
+
+    joined
+}
+
+/// The number a line starts with, after its indentation
+fn leading_number(line: &str) -> Option<f64> {
+    let line = line.trim_start();
+    let length = line
+        .char_indices()
+        .take_while(|(index, character)| character.is_ascii_digit() || *character == '.' || (*index == 0 && *character == '-'))
+        .count();
+
+    line[..length].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn sorts_in_every_order() {
+        let unsorted = lines(&["b", "10 x", "A", "2 y", "a"]);
+
+        assert_eq!(sort(unsorted.clone(), Order::Ascending), lines(&["10 x", "2 y", "A", "a", "b"]));
+        assert_eq!(sort(unsorted.clone(), Order::Descending), lines(&["b", "a", "A", "2 y", "10 x"]));
+        assert_eq!(sort(unsorted.clone(), Order::Numeric), lines(&["2 y", "10 x", "b", "A", "a"]));
+        assert_eq!(sort(unsorted, Order::CaseInsensitive), lines(&["10 x", "2 y", "A", "a", "b"]));
+    }
+
+    #[test]
+    fn sorts_negative_and_decimal_numbers() {
+        let unsorted = lines(&["1.5", "  -3", "x", "0.25"]);
+        assert_eq!(sort(unsorted, Order::Numeric), lines(&["  -3", "0.25", "1.5", "x"]));
+    }
+
+    #[test]
+    fn removes_repeated_lines() {
+        assert_eq!(unique(lines(&["a", "b", "a", "c", "b"])), lines(&["a", "b", "c"]));
+    }
+
+    #[test]
+    fn joins_lines() {
+        assert_eq!(join(&lines(&["fn main() {", "    run();", "", "}"])), "fn main() { run(); }");
+        assert_eq!(join(&lines(&["a ", "b"])), "a b");
+        assert_eq!(join(&lines(&["", "b"])), "b");
+        assert_eq!(join(&[]), "");
+    }
+
+    #[test]
+    fn only_sorting_and_friends_apply_to_the_document() {
+        assert!(Operation::Sort(Order::Ascending).applies_to_document());
+        assert!(Operation::Unique.applies_to_document());
+        assert!(!Operation::Delete.applies_to_document());
+    }
+}
@@ -13,10 +13,50 @@ use log::LevelFilter;
 use simplelog::TermLogger;
 use std::sync::LazyLock;
 
+/// Detection of the color scheme preferred by the desktop
+mod appearance;
+/// Bracket matching and auto-closing pairs
+mod brackets;
+/// Toggling line and block comments
+mod comment;
+/// Helpers to move the cursor of the editor
+mod cursor;
+/// The text of the open file, kept in a rope
+mod document;
+/// The dropdown window that slides in from an edge of the screen
+mod dropdown;
 /// The editor itself, as an iced application
 mod editor;
+/// Support for `.editorconfig` files
+mod editorconfig;
+/// Following a file as it grows
+mod follow;
+/// Parsing of the "go to line" input
+mod goto;
+/// The hex view of binary files
+mod hex;
+/// The syntax highlighter, with support for user themes and syntaxes
+mod highlighter;
+/// Undo and redo of the edits
+mod history;
+/// Automatic indentation of new lines
+mod indent;
+/// The key bindings of the editor commands
+mod keymap;
+/// Detection of the language of a document
+mod language;
+/// Viewing files that are too large to be loaded at once
+mod large_file;
+/// Operations on whole lines, like moving and sorting them
+mod lines;
+/// The command palette to search and run commands
+mod palette;
 /// The settings for the editor
 mod settings;
+/// The transformations that run over the text before it is saved
+mod transform;
+/// The size, position and visibility of the window
+mod window_state;
 use editor::Editor;
 
 /// The name of the application
@@ -54,17 +94,21 @@ pub fn main() -> iced::Result {
         eprintln!("Failed to initialize logger: {}", e);
     }
 
+    let app_settings = settings::Settings::new().expect("Failed to load settings");
+    let window_state = window_state::WindowState::load();
+
+    // The editor opens the window itself, it may close and reopen it while running in the background
     let window_settings = iced::window::Settings {
         icon: Some(iced::window::icon::from_file_data(LOGO, None).expect("Failed to load icon")),
+        // The window state is saved before the window is closed
+        exit_on_close_request: false,
         ..iced::window::Settings::default()
     };
 
-    iced::application(APP_NAME, Editor::update, Editor::view)
+    iced::daemon(APP_NAME, Editor::update, Editor::view)
         .subscription(Editor::subscription)
         .theme(Editor::theme)
         .font(include_bytes!("assets/fonts/icons.ttf").as_slice())
         .default_font(Font::MONOSPACE)
-        .centered()
-        .window(window_settings)
-        .run_with(Editor::new)
+        .run_with(move || Editor::new(app_settings, window_state, window_settings))
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+//! The command palette lists every editor command and lets the user run them by typing a part of their name.
+//! Commands are matched with a small fuzzy matcher, so `gtl` finds "Go to line".
+
+use crate::keymap::Command;
+
+/// The state of the open command palette
+#[derive(Debug, Default)]
+pub struct Palette {
+    /// The text the user typed
+    pub query: String,
+    /// The index of the highlighted entry in the matches
+    pub selected: usize,
+}
+
+impl Palette {
+    /// The commands matching the query, best match first
+    pub fn matches(&self) -> Vec<Command> {
+        let mut matches: Vec<(i32, Command)> = Command::ALL
+            .iter()
+            .filter_map(|command| {
+                let score = fuzzy_score(&self.query, command.title())
+                    .max(fuzzy_score(&self.query, command.name()))?;
+
+                Some((score, *command))
+            })
+            .collect();
+
+        // The sort is stable, so equal scores keep the order of `Command::ALL`
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(_, command)| command).collect()
+    }
+
+    /// Moves the highlighted entry by the given offset, wrapping around at the ends
+    pub fn move_selection(&mut self, offset: isize) {
+        let count = self.matches().len();
+
+        if count > 0 {
+            self.selected = (self.selected as isize + offset).rem_euclid(count as isize) as usize;
+        }
+    }
+
+    /// The highlighted command, if anything matches the query
+    pub fn selected(&self) -> Option<Command> {
+        self.matches().get(self.selected).copied()
+    }
+}
+
+/// Scores how well the query matches the candidate. Every character of the query has to appear in
+/// the candidate in the same order. Consecutive characters and characters at the start of a word
+/// score higher. Returns `None` if the candidate does not match.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let index = candidate[position..]
+            .iter()
+            .position(|c| *c == query_char)?
+            + position;
+
+        score += 1;
+
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        // Penalize skipped characters a little, so tighter matches win
+        score -= (index - position) as i32 / 4;
+
+        previous_match = Some(index);
+        position = index + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn characters_have_to_appear_in_order() {
+        assert!(fuzzy_score("gtl", "Go to line").is_some());
+        assert!(fuzzy_score("ltg", "Go to line").is_none());
+        assert!(fuzzy_score("x", "Go to line").is_none());
+    }
+
+    #[test]
+    fn case_and_spaces_are_ignored() {
+        assert_eq!(fuzzy_score("GO TO", "go to line"), fuzzy_score("goto", "Go to line"));
+    }
+
+    #[test]
+    fn an_empty_query_matches_everything() {
+        assert_eq!(fuzzy_score("", "Save file"), Some(0));
+    }
+
+    #[test]
+    fn word_starts_and_consecutive_characters_score_higher() {
+        let word_starts = fuzzy_score("sf", "Save file").unwrap();
+        let inside_words = fuzzy_score("ve", "Save file").unwrap();
+        assert!(word_starts > fuzzy_score("af", "Save file").unwrap());
+        assert!(fuzzy_score("save", "Save file").unwrap() > word_starts);
+        assert!(fuzzy_score("sa", "Save file").unwrap() > inside_words);
+    }
+
+    #[test]
+    fn the_best_match_comes_first() {
+        let palette = Palette { query: "line ending".to_owned(), selected: 0 };
+        assert_eq!(palette.selected(), Some(Command::SetLineEnding));
+
+        let palette = Palette { query: "charset".to_owned(), selected: 0 };
+        assert_eq!(palette.selected(), Some(Command::SetCharset));
+    }
+
+    #[test]
+    fn the_selection_wraps_around() {
+        let mut palette = Palette { query: "charset".to_owned(), selected: 0 };
+        let count = palette.matches().len();
+
+        palette.move_selection(-1);
+        assert_eq!(palette.selected, count - 1);
+        palette.move_selection(1);
+        assert_eq!(palette.selected, 0);
+    }
+}
@@ -2,13 +2,21 @@
 //! The settings are stored in a JSON file in the user's config directory. The settings are loaded when the application starts.
 //! Since you can't interact with the settings through the UI, you can change the settings by editing the JSON file directly.
 
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::path::PathBuf;
-use iced::highlighter;
 use serde::{Deserialize, Serialize};
 use directories::{ProjectDirs, UserDirs};
 use std::io::Read;
+use iced::{Color, Theme};
 
+use crate::appearance::ColorScheme;
+use crate::brackets;
+use crate::comment;
+use crate::dropdown::Edge;
+use crate::document::{Format, LineEnding};
+use crate::highlighter;
+use crate::indent;
 use crate::SETTINGS_FILE_NAME;
 
 /// The settings struct holds the settings of the application. It is serialized and deserialized to and from a JSON file.
@@ -19,10 +27,166 @@ pub struct Settings {
     file_path: Option<PathBuf>,
     /// The startup file path is the path of the file that is opened when the application starts.
     pub startup_file_path: PathBuf,
-    /// The theme is the color scheme of the application.
+    /// The theme is the color scheme of the application. Either a built-in theme, the file name of a `.tmTheme` in the themes directory
+    /// or `auto` to follow the color scheme of the desktop.
     theme: String,
+    /// The theme used by `auto` when the desktop prefers a light color scheme.
+    #[serde(default = "default_light_theme")]
+    light_theme: String,
+    /// The theme used by `auto` when the desktop prefers a dark color scheme.
+    #[serde(default = "default_dark_theme")]
+    dark_theme: String,
+    /// The color scheme preferred by the desktop, only used by the `auto` theme.
+    #[serde(skip)]
+    color_scheme: ColorScheme,
     /// Word wrap is a boolean that determines if the text should wrap at the end of the line.
     pub word_wrap: bool,
+    /// Files bigger than this many megabytes are opened in large file mode, without highlighting, word wrap and editing.
+    #[serde(default = "default_large_file_threshold")]
+    pub large_file_threshold: u64,
+    /// The font family of the editor. Fonts in the fonts directory next to the settings file can be used as well.
+    #[serde(default = "default_font_family")]
+    pub font_family: String,
+    /// The font size of the editor.
+    #[serde(default = "default_font_size")]
+    pub font_size: f32,
+    /// The line height of the editor, relative to the font size.
+    #[serde(default = "default_line_height")]
+    pub line_height: f32,
+    /// Whether changing the zoom writes the new font size to the settings file.
+    #[serde(default)]
+    pub persist_zoom: bool,
+    /// How the window is shown: `normal`, or `dropdown` to slide it in from an edge of the screen on the hotkey.
+    #[serde(default)]
+    pub window_mode: WindowMode,
+    /// The edge of the screen the dropdown window is attached to: `top`, `bottom`, `left` or `right`.
+    #[serde(default)]
+    pub dropdown_edge: Edge,
+    /// The share of the screen the dropdown window covers, in percent.
+    #[serde(default = "default_dropdown_size")]
+    pub dropdown_size: f32,
+    /// What happens to the window in the background: `hide` keeps it hidden, `close` closes it to free its memory.
+    #[serde(default)]
+    pub background_mode: BackgroundMode,
+    /// Whether the window is hidden when it loses the focus.
+    #[serde(default)]
+    pub hide_on_blur: bool,
+    /// Whether pressing Escape in the editor hides the window.
+    #[serde(default)]
+    pub escape_hides: bool,
+    /// Whether a modified file is saved before the window is hidden by `hide_on_blur` or `escape_hides`.
+    #[serde(default)]
+    pub save_on_hide: bool,
+    /// Whether a new line starts with the indentation of the line before it, deeper after an opener like `{`.
+    #[serde(default = "default_true")]
+    pub auto_indent: bool,
+    /// Whether typing an opening bracket or quote inserts the closing one too
+    #[serde(default = "default_true")]
+    pub auto_close: bool,
+    /// The width of a level of indentation. Tab characters are drawn by the text editor at its own fixed width.
+    #[serde(default = "default_tab_width")]
+    pub tab_width: usize,
+    /// Whether Tab and the automatic indentation insert spaces instead of a tab
+    #[serde(default = "default_true")]
+    pub insert_spaces: bool,
+    /// Whether whitespace at the end of lines is removed when a file is saved
+    #[serde(default)]
+    pub trim_trailing_whitespace: bool,
+    /// Whether a saved file ends with exactly one line break
+    #[serde(default = "default_true")]
+    pub insert_final_newline: bool,
+    /// The line ending files are saved with
+    #[serde(default)]
+    pub line_ending: LineEnding,
+    /// Settings for single languages, keyed by the language shown in the status bar, e.g. `"py"`.
+    #[serde(default)]
+    languages: HashMap<String, LanguageSettings>,
+    /// The key bindings override the default key chords of the editor commands, e.g. `"save_file": "ctrl+shift+s"`.
+    #[serde(default)]
+    pub keybindings: HashMap<String, String>,
+    /// Overrides of the user interface colors. Colors that are not set are derived from the theme.
+    #[serde(default)]
+    ui_colors: UiColors,
+}
+
+/// How the window is shown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowMode {
+    /// A regular window, remembering its size and position
+    #[default]
+    Normal,
+    /// An undecorated window that slides in from an edge of the screen
+    Dropdown,
+}
+
+/// What happens to the window while the editor runs in the background
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundMode {
+    /// The window is hidden, so it shows up again instantly
+    #[default]
+    Hide,
+    /// The window and its buffers are closed and opened again by the hotkey, using less memory
+    Close,
+}
+
+/// Settings that can be changed for a single language. Settings that are not set fall back to the global ones.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LanguageSettings {
+    /// Whether new lines are indented automatically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auto_indent: Option<bool>,
+    /// The line endings after which the next line is indented one level deeper, e.g. `[":"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    indent_after: Option<Vec<String>>,
+    /// Whether opening brackets and quotes are closed automatically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auto_close: Option<bool>,
+    /// The pairs that are closed automatically, each written as its two characters, e.g. `["()", "**"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auto_close_pairs: Option<Vec<String>>,
+    /// The width of a level of indentation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tab_width: Option<usize>,
+    /// Whether spaces are inserted instead of a tab
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    insert_spaces: Option<bool>,
+    /// Whether whitespace at the end of lines is removed on save
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trim_trailing_whitespace: Option<bool>,
+    /// Whether a saved file ends with exactly one line break
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    insert_final_newline: Option<bool>,
+    /// The line ending files are saved with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line_ending: Option<LineEnding>,
+    /// The token that starts a line comment, e.g. `"//"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line_comment: Option<String>,
+    /// The tokens that open and close a block comment, e.g. `["/*", "*/"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block_comment: Option<(String, String)>,
+}
+
+/// The user interface colors, as hex strings like `#268bd2`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UiColors {
+    /// The background of the window and the editor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    background: Option<String>,
+    /// The color of the text outside the editor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    /// The color of buttons and the selection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    primary: Option<String>,
+    /// The color of success messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    success: Option<String>,
+    /// The color of errors and warnings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    danger: Option<String>,
 }
 
 impl Default for Settings {
@@ -44,7 +208,32 @@ impl Default for Settings {
             file_path: None,
             startup_file_path: document_dir_pathbuf,
             theme: "solarized".to_owned(),
+            light_theme: default_light_theme(),
+            dark_theme: default_dark_theme(),
+            color_scheme: ColorScheme::default(),
             word_wrap: true,
+            large_file_threshold: default_large_file_threshold(),
+            font_family: default_font_family(),
+            font_size: default_font_size(),
+            line_height: default_line_height(),
+            persist_zoom: false,
+            window_mode: WindowMode::default(),
+            dropdown_edge: Edge::default(),
+            dropdown_size: default_dropdown_size(),
+            background_mode: BackgroundMode::default(),
+            hide_on_blur: false,
+            escape_hides: false,
+            save_on_hide: false,
+            auto_indent: true,
+            auto_close: true,
+            tab_width: default_tab_width(),
+            insert_spaces: true,
+            trim_trailing_whitespace: false,
+            insert_final_newline: true,
+            line_ending: LineEnding::Lf,
+            languages: HashMap::new(),
+            keybindings: HashMap::new(),
+            ui_colors: UiColors::default(),
         }
     }
 }
@@ -52,16 +241,25 @@ impl Default for Settings {
 impl Settings {
     /// Set the path of the settings file. This is called when the settings are loaded.
     fn set_path(&mut self) -> Result<(), String> {
-        #[cfg(not(target_os = "windows"))]
-        let project_dirs = ProjectDirs::from("sbt", "slightlybettertext", "slightlybettertext").ok_or("Could not find project directories".to_owned()).unwrap();
-        #[cfg(target_os = "windows")]
-        let project_dirs = ProjectDirs::from("sbt", "slightlybettertext", "").ok_or("Could not find project directories".to_owned()).unwrap();
-        let config_dir = project_dirs.config_dir().to_owned();
+        let config_dir = config_dir();
         fs::create_dir_all(&config_dir).map_err(|error| format!("Failed to create config dir: {error}")).unwrap();
         self.file_path = Some(config_dir);
         Ok(())
     }
 
+    /// Writes a new font size to the settings file. Only this value is changed, so runtime changes
+    /// like the theme picked with `next_theme` are not persisted along with it.
+    pub fn persist_font_size(&self, font_size: f32) -> Result<(), String> {
+        let path = self.file_path.as_ref().ok_or("No file path set".to_owned())?.join(SETTINGS_FILE_NAME);
+
+        let contents = fs::read_to_string(&path).map_err(|error| format!("Failed to read settings file: {error}"))?;
+        let mut value: serde_json::Value = serde_json::from_str(&contents).map_err(|error| format!("Failed to deserialize settings: {error}"))?;
+        value["font_size"] = serde_json::json!(font_size);
+
+        let file = File::create(path).map_err(|error| format!("Failed to create settings file: {error}"))?;
+        serde_json::to_writer_pretty(file, &value).map_err(|error| format!("Failed to serialize settings: {error}"))
+    }
+
     /// Save the settings to the settings file. This is called when we just created the settings file.
     fn save(&self) -> Result<(), String> {
         let file_path = self.file_path.as_ref().ok_or("No file path set".to_owned()).unwrap();
@@ -100,15 +298,214 @@ impl Settings {
         Ok(settings_from_str)
     }
 
-    /// A helper function to convert the iced theme to and from a string.
+    /// A helper function to convert the theme name to a highlighter theme.
     pub fn get_theme(&self) -> highlighter::Theme {
-        match self.theme.to_lowercase().as_str() {
-            "eighties" => highlighter::Theme::Base16Eighties,
-            "mocha" => highlighter::Theme::Base16Mocha,
-            "ocean" => highlighter::Theme::Base16Ocean,
-            "github" | "inspired github" => highlighter::Theme::InspiredGitHub,
-            "solarized" | "solarized dark" => highlighter::Theme::SolarizedDark,
-            _ => highlighter::Theme::SolarizedDark,
+        if self.follows_color_scheme() {
+            match self.color_scheme {
+                ColorScheme::Light => highlighter::Theme::from_name(&self.light_theme),
+                ColorScheme::Dark => highlighter::Theme::from_name(&self.dark_theme),
+            }
+        } else {
+            highlighter::Theme::from_name(&self.theme)
+        }
+    }
+
+    /// Whether the theme follows the color scheme of the desktop.
+    pub fn follows_color_scheme(&self) -> bool {
+        self.theme.eq_ignore_ascii_case("auto")
+    }
+
+    /// Sets the color scheme preferred by the desktop, which picks the theme when following it.
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        self.color_scheme = color_scheme;
+    }
+
+    /// The theme of the user interface, derived from the highlighter theme and the color overrides.
+    pub fn get_app_theme(&self) -> Theme {
+        let theme = self.get_theme();
+        let mut palette = theme.palette();
+
+        for (color, value) in [
+            (&mut palette.background, &self.ui_colors.background),
+            (&mut palette.text, &self.ui_colors.text),
+            (&mut palette.primary, &self.ui_colors.primary),
+            (&mut palette.success, &self.ui_colors.success),
+            (&mut palette.danger, &self.ui_colors.danger),
+        ] {
+            if let Some(value) = value {
+                match Color::parse(value) {
+                    Some(parsed) => *color = parsed,
+                    None => log::warn!("Invalid color in ui_colors: {}", value),
+                }
+            }
+        }
+
+        Theme::custom(theme.name().to_owned(), palette)
+    }
+
+    /// The settings of a language, if the user changed any
+    fn language_settings(&self, language: &str) -> Option<&LanguageSettings> {
+        self.languages
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(language))
+            .map(|(_, settings)| settings)
+    }
+
+    /// Whether new lines of the language are indented automatically.
+    pub fn auto_indent(&self, language: &str) -> bool {
+        self.language_settings(language)
+            .and_then(|settings| settings.auto_indent)
+            .unwrap_or(self.auto_indent)
+    }
+
+    /// The line endings after which the next line of the language is indented one level deeper.
+    pub fn indent_after(&self, language: &str) -> Vec<String> {
+        self.language_settings(language)
+            .and_then(|settings| settings.indent_after.clone())
+            .unwrap_or_else(|| indent::default_openers(language).iter().map(|opener| opener.to_string()).collect())
+    }
+
+    /// The pairs of characters that are closed automatically in the language, none if auto-closing is off
+    pub fn auto_close_pairs(&self, language: &str) -> Vec<(char, char)> {
+        let settings = self.language_settings(language);
+
+        if !settings.and_then(|settings| settings.auto_close).unwrap_or(self.auto_close) {
+            return Vec::new();
+        }
+
+        match settings.and_then(|settings| settings.auto_close_pairs.as_ref()) {
+            Some(pairs) => pairs
+                .iter()
+                .filter_map(|pair| {
+                    let mut characters = pair.chars();
+                    match (characters.next(), characters.next(), characters.next()) {
+                        (Some(open), Some(close), None) => Some((open, close)),
+                        _ => {
+                            log::warn!("Ignoring the auto-close pair {:?}, it must be two characters", pair);
+                            None
+                        }
+                    }
+                })
+                .collect(),
+            None => brackets::default_pairs(language).to_vec(),
+        }
+    }
+
+    /// How files of the language are indented, unless a file uses another style
+    pub fn indentation(&self, language: &str) -> indent::Indentation {
+        let settings = self.language_settings(language);
+
+        indent::Indentation {
+            tab_width: settings
+                .and_then(|settings| settings.tab_width)
+                .unwrap_or(self.tab_width)
+                .max(1),
+            insert_spaces: settings
+                .and_then(|settings| settings.insert_spaces)
+                .unwrap_or(self.insert_spaces),
+        }
+    }
+
+    /// Whether whitespace at the end of lines is removed when a file of the language is saved
+    pub fn trim_trailing_whitespace(&self, language: &str) -> bool {
+        self.language_settings(language)
+            .and_then(|settings| settings.trim_trailing_whitespace)
+            .unwrap_or(self.trim_trailing_whitespace)
+    }
+
+    /// Whether a saved file of the language ends with exactly one line break
+    pub fn insert_final_newline(&self, language: &str) -> bool {
+        self.language_settings(language)
+            .and_then(|settings| settings.insert_final_newline)
+            .unwrap_or(self.insert_final_newline)
+    }
+
+    /// The tokens that comment out code of the language
+    pub fn comment_tokens(&self, language: &str) -> comment::Tokens {
+        let defaults = comment::default_tokens(language);
+        let settings = self.language_settings(language);
+
+        comment::Tokens {
+            line: settings.and_then(|settings| settings.line_comment.clone()).or(defaults.line),
+            block: settings.and_then(|settings| settings.block_comment.clone()).or(defaults.block),
         }
     }
+
+    /// The format files of the language are written in
+    pub fn format(&self, language: &str) -> Format {
+        Format {
+            line_ending: self
+                .language_settings(language)
+                .and_then(|settings| settings.line_ending)
+                .unwrap_or(self.line_ending),
+            ..Format::default()
+        }
+    }
+
+    /// Switches to the next available theme. The change is not written to the settings file.
+    pub fn next_theme(&mut self) {
+        let current = self.get_theme();
+        let names: Vec<&str> = highlighter::Theme::names().collect();
+        let index = names
+            .iter()
+            .position(|name| *name == current.name())
+            .unwrap_or(0);
+
+        self.theme = names[(index + 1) % names.len()].to_owned();
+        log::info!("Switched theme to: {}", self.theme);
+    }
+}
+
+/// The default size above which files are opened in large file mode, in megabytes
+fn default_large_file_threshold() -> u64 {
+    50
+}
+
+/// Used for settings that are on unless turned off
+fn default_true() -> bool {
+    true
+}
+
+/// The default width of a level of indentation
+fn default_tab_width() -> usize {
+    4
+}
+
+/// The default font family, the monospace font of the system
+fn default_font_family() -> String {
+    "monospace".to_owned()
+}
+
+/// The default font size, the same as the iced default
+fn default_font_size() -> f32 {
+    16.0
+}
+
+/// The default relative line height, the same as the iced default
+fn default_line_height() -> f32 {
+    1.3
+}
+
+/// The default size of the dropdown window, in percent of the screen
+fn default_dropdown_size() -> f32 {
+    40.0
+}
+
+/// The default theme for a light desktop
+fn default_light_theme() -> String {
+    "github".to_owned()
+}
+
+/// The default theme for a dark desktop
+fn default_dark_theme() -> String {
+    "solarized".to_owned()
+}
+
+/// The directory of the settings file. The user themes and syntaxes are loaded from here as well.
+pub fn config_dir() -> PathBuf {
+    #[cfg(not(target_os = "windows"))]
+    let project_dirs = ProjectDirs::from("sbt", "slightlybettertext", "slightlybettertext").ok_or("Could not find project directories".to_owned()).unwrap();
+    #[cfg(target_os = "windows")]
+    let project_dirs = ProjectDirs::from("sbt", "slightlybettertext", "").ok_or("Could not find project directories".to_owned()).unwrap();
+    project_dirs.config_dir().to_owned()
 }
\ No newline at end of file
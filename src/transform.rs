@@ -0,0 +1,132 @@
+//! The transformations that run over the text before it is saved. They are applied to the text editor as an edit,
+//! so the text in the editor matches the file on disk. The text is transformed a line at a time, and only the lines
+//! that change are copied.
+
+/// A transformation of the lines of the text. An empty last line stands for a final line break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Turns carriage returns left over from pasted text into line breaks
+    NormalizeLineEndings,
+    /// Removes whitespace at the end of every line
+    TrimTrailingWhitespace,
+    /// Makes the text end with exactly one line break
+    FinalNewline,
+    /// Removes the line breaks at the end of the text
+    NoFinalNewline,
+}
+
+/// The steps to run, in order. A final line break is added (`true`), removed, or left alone.
+pub fn pipeline(trim_trailing_whitespace: bool, final_newline: Option<bool>) -> Vec<Step> {
+    let mut steps = vec![Step::NormalizeLineEndings];
+
+    if trim_trailing_whitespace {
+        steps.push(Step::TrimTrailingWhitespace);
+    }
+
+    match final_newline {
+        Some(true) => steps.push(Step::FinalNewline),
+        Some(false) => steps.push(Step::NoFinalNewline),
+        None => {}
+    }
+
+    steps
+}
+
+/// Runs the steps that work on single lines over a line. Returns the lines it turns into, or `None` if it is unchanged.
+pub fn line(steps: &[Step], line: &str) -> Option<Vec<String>> {
+    let splits = line.contains('\r') && steps.contains(&Step::NormalizeLineEndings);
+    let trims = line.ends_with(char::is_whitespace) && steps.contains(&Step::TrimTrailingWhitespace);
+
+    // Most lines are left alone, they are not copied
+    if !splits && !trims {
+        return None;
+    }
+
+    let mut lines = vec![line.to_owned()];
+
+    for step in steps {
+        match step {
+            Step::NormalizeLineEndings if line.contains('\r') => {
+                let text = line.strip_suffix('\r').unwrap_or(line);
+                lines = text.split('\r').map(str::to_owned).collect();
+            }
+            Step::TrimTrailingWhitespace => {
+                for line in &mut lines {
+                    line.truncate(line.trim_end().len());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (lines.len() != 1 || lines[0] != line).then_some(lines)
+}
+
+/// How many empty lines the text should end with, given its amount of lines and how many empty lines it ends with.
+/// An empty last line stands for a final line break. Returns `None` if the steps leave the end of the text alone.
+pub fn final_empty_lines(steps: &[Step], line_count: usize, empty_lines: usize) -> Option<usize> {
+    let wanted = steps.iter().rev().find_map(|step| match step {
+        Step::FinalNewline => Some(1),
+        Step::NoFinalNewline => Some(0),
+        _ => None,
+    })?;
+
+    // A text without any other line keeps one empty line
+    Some(if empty_lines >= line_count { 1 } else { wanted })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> Option<Vec<String>> {
+        Some(lines.iter().map(|line| line.to_string()).collect())
+    }
+
+    #[test]
+    fn builds_the_pipeline() {
+        assert_eq!(pipeline(false, None), vec![Step::NormalizeLineEndings]);
+        assert_eq!(
+            pipeline(true, Some(true)),
+            vec![Step::NormalizeLineEndings, Step::TrimTrailingWhitespace, Step::FinalNewline]
+        );
+        assert_eq!(pipeline(false, Some(false)), vec![Step::NormalizeLineEndings, Step::NoFinalNewline]);
+    }
+
+    #[test]
+    fn trims_trailing_whitespace() {
+        let steps = pipeline(true, None);
+
+        assert_eq!(line(&steps, "a \t"), lines(&["a"]));
+        assert_eq!(line(&steps, "  "), lines(&[""]));
+        assert_eq!(line(&steps, "  a"), None);
+        assert_eq!(line(&pipeline(false, None), "a "), None);
+    }
+
+    #[test]
+    fn normalizes_line_endings() {
+        let steps = pipeline(true, None);
+
+        assert_eq!(line(&steps, "a\r"), lines(&["a"]));
+        assert_eq!(line(&steps, "a \rb\rc"), lines(&["a", "b", "c"]));
+    }
+
+    #[test]
+    fn ends_with_one_line_break() {
+        let steps = pipeline(false, Some(true));
+
+        assert_eq!(final_empty_lines(&steps, 3, 0), Some(1));
+        assert_eq!(final_empty_lines(&steps, 5, 3), Some(1));
+        assert_eq!(final_empty_lines(&steps, 2, 2), Some(1));
+        assert_eq!(final_empty_lines(&pipeline(false, None), 3, 0), None);
+    }
+
+    #[test]
+    fn ends_without_a_line_break() {
+        let steps = pipeline(false, Some(false));
+
+        assert_eq!(final_empty_lines(&steps, 3, 1), Some(0));
+        assert_eq!(final_empty_lines(&steps, 3, 0), Some(0));
+        assert_eq!(final_empty_lines(&steps, 1, 1), Some(1));
+    }
+}
@@ -0,0 +1,89 @@
+//! The window state remembers the size, position and visibility of the window between restarts.
+//! It is stored in its own file next to the settings file, since it changes without the user editing anything.
+
+use iced::window;
+use iced::{Point, Size};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+
+use crate::settings;
+
+/// The name of the window state file
+static WINDOW_STATE_FILE_NAME: &str = "window.json";
+
+/// The geometry and visibility of the window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    /// The width of the window, while it is not maximized
+    pub width: f32,
+    /// The height of the window, while it is not maximized
+    pub height: f32,
+    /// The position of the window, or `None` to center it
+    pub position: Option<(f32, f32)>,
+    /// Whether the window is maximized
+    pub maximized: bool,
+    /// Whether the window was visible, or hidden in the background
+    pub visible: bool,
+}
+
+impl Default for WindowState {
+    /// The default state is a centered window of the default size
+    fn default() -> Self {
+        let size = window::Settings::default().size;
+
+        WindowState {
+            width: size.width,
+            height: size.height,
+            position: None,
+            maximized: false,
+            visible: true,
+        }
+    }
+}
+
+impl WindowState {
+    /// Loads the window state from its file. Falls back to the default state if there is none.
+    pub fn load() -> Self {
+        let path = settings::config_dir().join(WINDOW_STATE_FILE_NAME);
+
+        let state = fs::read_to_string(&path)
+            .map_err(|error| format!("Failed to read window state: {error}"))
+            .and_then(|contents| serde_json::from_str(&contents).map_err(|error| format!("Failed to deserialize window state: {error}")));
+
+        match state {
+            Ok(state) => {
+                log::debug!("Loaded window state: {:?}", state);
+                state
+            }
+            Err(error) => {
+                if path.exists() {
+                    log::warn!("{}", error);
+                }
+                WindowState::default()
+            }
+        }
+    }
+
+    /// Writes the window state to its file
+    pub fn save(&self) -> Result<(), String> {
+        let config_dir = settings::config_dir();
+        fs::create_dir_all(&config_dir).map_err(|error| format!("Failed to create config dir: {error}"))?;
+
+        let file = File::create(config_dir.join(WINDOW_STATE_FILE_NAME)).map_err(|error| format!("Failed to create window state file: {error}"))?;
+        serde_json::to_writer_pretty(file, self).map_err(|error| format!("Failed to serialize window state: {error}"))
+    }
+
+    /// Applies the size, position and visibility to the settings of the window.
+    /// Maximizing has to be done once the window exists.
+    pub fn apply(&self, settings: window::Settings) -> window::Settings {
+        window::Settings {
+            size: Size::new(self.width, self.height),
+            position: match self.position {
+                Some((x, y)) => window::Position::Specific(Point::new(x, y)),
+                None => window::Position::Centered,
+            },
+            visible: self.visible,
+            ..settings
+        }
+    }
+}